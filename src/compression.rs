@@ -0,0 +1,141 @@
+//! Transparent decompression of the files, according to their extension
+//!
+//! Files whose name ends with `.gz` are decompressed if the `gzip` feature is enabled (which it
+//! is by default), those whose name ends with `.bz2` if the `bzip2` feature is, and those whose
+//! name ends with `.xz` if the `xz` feature is; all other files are read as they are.
+
+use std::io::{BufRead, BufReader, Read};
+
+#[cfg(feature = "bzip2")]
+use bzip2::read::MultiBzDecoder;
+#[cfg(feature = "gzip")]
+use flate2::read::MultiGzDecoder;
+#[cfg(feature = "xz")]
+use xz2::read::XzDecoder;
+
+/// Compression of a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    /// not compressed, or in a format which is not supported
+    None,
+    /// gzip
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// bzip2
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+    /// xz
+    #[cfg(feature = "xz")]
+    Xz,
+}
+
+impl Compression {
+    /// Name of the format, as used in the errors
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::None => "plain text",
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => "gzip",
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => "bzip2",
+            #[cfg(feature = "xz")]
+            Compression::Xz => "xz",
+        }
+    }
+}
+
+/// Extensions of the supported formats
+const EXTENSIONS: &[(&str, Compression)] = &[
+    #[cfg(feature = "gzip")]
+    (".gz", Compression::Gzip),
+    #[cfg(feature = "bzip2")]
+    (".bz2", Compression::Bzip2),
+    #[cfg(feature = "xz")]
+    (".xz", Compression::Xz),
+];
+
+/// Compression of a file, given by the extension of its name
+pub fn compression(filename: &str) -> Compression {
+    EXTENSIONS.iter()
+              .find(|(extension, _)| filename.ends_with(extension))
+              .map_or(Compression::None, |&(_, compression)| compression)
+}
+
+/// Check if a file is decompressed when read
+pub fn is_compressed(filename: &str) -> bool {
+    compression(filename) != Compression::None
+}
+
+/// Buffered reader of a file, decompressing it according to the extension of its name
+pub fn reader(filename: &str, file: impl Read + 'static) -> Box<dyn BufRead> {
+    match compression(filename) {
+        Compression::None => Box::new(BufReader::new(file)),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Box::new(BufReader::new(MultiBzDecoder::new(file))),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Box::new(BufReader::new(XzDecoder::new_multi_decoder(file))),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(any(feature = "gzip", feature = "bzip2", feature = "xz"))]
+    use std::{env, fs, fs::File};
+
+    /// Decompress `compressed` with the reader of a file named `filename`
+    #[cfg(any(feature = "gzip", feature = "bzip2", feature = "xz"))]
+    fn decompress(filename: &str, compressed: Vec<u8>) -> String {
+        let path = env::temp_dir().join(format!("minigrep_reader_{}_{}", std::process::id(), 
+                                                filename));
+        fs::write(&path, compressed).unwrap();
+        let name = path.to_str().unwrap();
+        assert!(is_compressed(name));
+        let mut content = String::new();
+        reader(name, File::open(&path).unwrap()).read_to_string(&mut content).unwrap();
+        fs::remove_file(&path).unwrap();
+        content
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn reader_gzip() {
+        use std::io::Write;
+        use flate2::write::GzEncoder;
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"compressed\nlines\n").unwrap();
+        assert_eq!("compressed\nlines\n", decompress("file.txt.gz", encoder.finish().unwrap()));
+        assert_eq!("gzip", compression("file.gz").name());
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn reader_bzip2() {
+        use std::io::Write;
+        use bzip2::write::BzEncoder;
+        let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(b"compressed\nlines\n").unwrap();
+        assert_eq!("compressed\nlines\n", decompress("file.txt.bz2", encoder.finish().unwrap()));
+        assert_eq!("bzip2", compression("file.bz2").name());
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn reader_xz() {
+        use std::io::Write;
+        use xz2::write::XzEncoder;
+        let mut encoder = XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(b"compressed\nlines\n").unwrap();
+        assert_eq!("compressed\nlines\n", decompress("file.txt.xz", encoder.finish().unwrap()));
+        assert_eq!("xz", compression("file.xz").name());
+    }
+
+    #[test]
+    fn not_compressed() {
+        assert!(!is_compressed("poem.txt"));
+        assert!(!is_compressed("-"));
+    }
+}
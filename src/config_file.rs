@@ -0,0 +1,245 @@
+//! Default options read from a `.minigreprc` file
+//!
+//! The file is written in a subset of TOML: each line is empty, a comment starting with `#`, or
+//! `key = value`, where the key is the long name of a flag without the leading `--` (with `-` or
+//! `_` between the words) and the value is a boolean, an integer, a string in double quotes, or
+//! an array of them. For instance:
+//!
+//! ```toml
+//! # always print the line numbers, and smart case by default
+//! line-number = true
+//! smart-case = true
+//! style = [1, 4]
+//! color = "never"
+//! ```
+//!
+//! Each entry is turned into the flag of the same name: `true` gives the flag alone, `false`
+//! nothing, and the other values are given to the flag after `=`, the elements of an array
+//! being separated by commas. The flags setting the patterns, listed in `PATTERN_FLAGS`, are 
+//! not accepted, as the first positional argument would then be taken as a file name.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// name of the configuration file
+pub const FILE_NAME: &str = ".minigreprc";
+
+/// flags setting the patterns, which can not be given in the configuration file
+pub const PATTERN_FLAGS: [&str; 3] = ["--pattern", "--any", "--file"];
+
+/// Path of the configuration file, in the current directory or else in the home directory
+///
+/// Return `None` if there is no such file.
+pub fn find() -> Option<PathBuf> {
+    let current = Path::new(FILE_NAME);
+    if current.is_file() {
+        return Some(current.to_path_buf());
+    }
+    let home = Path::new(&env::var_os("HOME")?).join(FILE_NAME);
+    if home.is_file() {
+        Some(home)
+    } else {
+        None
+    }
+}
+
+/// Read a configuration file and turn its entries into flags
+///
+/// # Errors
+///
+/// The reason why the file can not be read or parsed, with the number of the faulty line
+pub fn load(path: &Path) -> Result<Vec<String>, String> {
+    match fs::read_to_string(path) {
+        Ok(content) => parse(&content),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Turn the entries of a configuration file into flags
+///
+/// # Errors
+///
+/// The reason why the content can not be parsed, with the number of the faulty line
+pub fn parse(content: &str) -> Result<Vec<String>, String> {
+    let mut flags = Vec::<String>::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |reason: &str| format!("line {}: {}", i + 1, reason);
+        if line.starts_with('[') {
+            return Err(error("tables are not supported"));
+        }
+        let (key, value) = match line.find('=') {
+            Some(j) => (line[..j].trim(), line[j+1..].trim()),
+            None => return Err(error("expected `key = value`")),
+        };
+        let valid_key = key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if key.is_empty() || !valid_key {
+            return Err(error(&format!("invalid key {}", key)));
+        }
+        let flag = format!("--{}", key.replace('_', "-"));
+        match parse_value(value).map_err(|reason| error(&reason))? {
+            Value::Bool(true) => flags.push(flag),
+            Value::Bool(false) => (),
+            Value::Text(text) => flags.push(format!("{}={}", flag, text)),
+        }
+    }
+    Ok(flags)
+}
+
+/// Check if a flag read from the configuration file sets the patterns
+pub fn sets_patterns(flag: &str) -> bool {
+    let name = flag.split('=').next().unwrap_or(flag);
+    PATTERN_FLAGS.contains(&name)
+}
+
+/// A value of the configuration file
+#[derive(Debug, PartialEq)]
+enum Value {
+    /// a boolean
+    Bool(bool),
+    /// any other value, as given to the flag
+    Text(String),
+}
+
+/// Parse a value, followed by an optional comment
+fn parse_value(value: &str) -> Result<Value, String> {
+    let (value, rest) = if let Some(array) = value.strip_prefix('[') {
+        let end = match closing_bracket(array) {
+            Some(end) => end,
+            None => return Err("unterminated array".to_string()),
+        };
+        let mut elements = Vec::<String>::new();
+        for element in split_elements(&array[..end]) {
+            match parse_scalar(element)? {
+                (Value::Text(text), "") => elements.push(text),
+                (Value::Bool(_), "") => {
+                    return Err("arrays of booleans are not supported".to_string());
+                },
+                _ => return Err(format!("invalid array element {}", element)),
+            }
+        }
+        (Value::Text(elements.join(",")), array[end+1..].trim())
+    } else {
+        parse_scalar(value)?
+    };
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(value)
+    } else {
+        Err(format!("unexpected {} after the value", rest))
+    }
+}
+
+/// Parse a scalar value at the start of a string, returning it with the rest of the string
+fn parse_scalar(value: &str) -> Result<(Value, &str), String> {
+    if let Some(string) = value.strip_prefix('"') {
+        let mut text = String::new();
+        let mut chars = string.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::Text(text), string[i+1..].trim())),
+                '\\' => match chars.next() {
+                    Some((_, '"')) => text.push('"'),
+                    Some((_, '\\')) => text.push('\\'),
+                    Some((_, 'n')) => text.push('\n'),
+                    Some((_, 't')) => text.push('\t'),
+                    _ => return Err("invalid escape sequence".to_string()),
+                },
+                c => text.push(c),
+            }
+        }
+        return Err("unterminated string".to_string());
+    }
+    let end = value.find(|c: char| c.is_whitespace() || c == '#').unwrap_or(value.len());
+    let (word, rest) = (&value[..end], value[end..].trim());
+    match word {
+        "true" => Ok((Value::Bool(true), rest)),
+        "false" => Ok((Value::Bool(false), rest)),
+        _ if !word.is_empty() && word.parse::<i64>().is_ok() => {
+            Ok((Value::Text(word.to_string()), rest))
+        },
+        _ => Err(format!("invalid value {}", value)),
+    }
+}
+
+/// Position of the bracket closing an array, ignoring those in strings
+fn closing_bracket(array: &str) -> Option<usize> {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in array.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ']' if !in_string => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Split the content of an array at the commas which are not in strings
+fn split_elements(content: &str) -> Vec<&str> {
+    let mut elements = Vec::<&str>::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in content.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                elements.push(content[start..i].trim());
+                start = i + 1;
+            },
+            _ => (),
+        }
+    }
+    // a trailing comma is allowed
+    let last = content[start..].trim();
+    if !last.is_empty() {
+        elements.push(last);
+    }
+    elements
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_entries() {
+        let content = "# defaults\n\nline-number = true\nsmart_case = true # comment\n\
+                       json = false\nstyle = [1, 4]\ncolor = \"never\"\ncontext = 2\n";
+        assert_eq!(Ok(vec!["--line-number".to_string(), "--smart-case".to_string(),
+                           "--style=1,4".to_string(), "--color=never".to_string(),
+                           "--context=2".to_string()]),
+                   parse(content));
+        assert_eq!(Ok(vec!["--pattern=a, \"b\"".to_string()]),
+                   parse("pattern = \"a, \\\"b\\\"\""));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err("line 2: expected `key = value`".to_string()), parse("\nline-number\n"));
+        assert_eq!(Err("line 1: unterminated string".to_string()), parse("color = \"never"));
+        assert_eq!(Err("line 1: invalid value yes".to_string()), parse("json = yes"));
+        assert_eq!(Err("line 1: tables are not supported".to_string()), parse("[flags]"));
+        assert!(parse("style = [1, 4").is_err());
+        assert!(parse("style = 1 4").is_err());
+    }
+
+    #[test]
+    fn pattern_flags() {
+        assert!(sets_patterns("--file=patterns.txt"));
+        assert!(sets_patterns("--pattern=a=b"));
+        assert!(sets_patterns("--any=a,b"));
+        assert!(!sets_patterns("--files-from=list.txt"));
+        assert!(!sets_patterns("--line-number"));
+    }
+}
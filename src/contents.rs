@@ -0,0 +1,86 @@
+//! Content of a file read at once
+//!
+//! The content is either copied to memory or, if the `mmap` feature is enabled, mapped from the
+//! file; in both cases, the lines borrow from it instead of being copied again.
+
+use std::fmt;
+
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+
+/// Valid UTF-8 content of a file
+pub enum Contents {
+    /// content copied to memory
+    Owned(String),
+    /// memory-mapped file, checked to be valid UTF-8 when mapped
+    #[cfg(feature = "mmap")]
+    Mapped(Mmap),
+}
+
+impl Contents {
+    /// Map a file, checking that its content is valid UTF-8
+    ///
+    /// Return `None` if the content is not valid UTF-8.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn mapped(map: Mmap) -> Option<Contents> {
+        match std::str::from_utf8(&map) {
+            Ok(_) => Some(Contents::Mapped(map)),
+            Err(_) => None,
+        }
+    }
+
+    /// The whole content
+    pub fn as_str(&self) -> &str {
+        match self {
+            Contents::Owned(content) => content,
+            // SAFETY: the mapping was checked to be valid UTF-8 when created
+            #[cfg(feature = "mmap")]
+            Contents::Mapped(map) => unsafe { std::str::from_utf8_unchecked(map) },
+        }
+    }
+
+    /// The lines, without their line terminators (`\n` or `\r\n`)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use minigrep::contents::Contents;
+    ///
+    /// let contents = Contents::Owned("Rust:\r\nsafe, fast, productive.\n".to_string());
+    /// assert_eq!(vec!["Rust:", "safe, fast, productive."], contents.lines());
+    /// ```
+    pub fn lines(&self) -> Vec<&str> {
+        crate::split_lines(self.as_str())
+    }
+
+    /// Copy the lines to owned strings
+    pub fn to_lines(&self) -> Vec<String> {
+        self.lines().into_iter().map(String::from).collect()
+    }
+}
+
+impl fmt::Debug for Contents {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Contents").field(&self.as_str()).finish()
+    }
+}
+
+impl PartialEq for Contents {
+    fn eq(&self, other: &Contents) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owned_lines() {
+        let contents = Contents::Owned("a\r\nb\n\nc".to_string());
+        assert_eq!(vec!["a", "b", "", "c"], contents.lines());
+        assert_eq!(vec!["a".to_string(), "b".to_string(), "".to_string(), "c".to_string()],
+                   contents.to_lines());
+    }
+}
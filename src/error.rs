@@ -0,0 +1,108 @@
+//! Errors which can occur while parsing the arguments or reading the files
+
+use std::error::Error;
+use std::fmt;
+
+/// An error of minigrep
+///
+/// The messages given by `Display` are not styled; colouring them is left to the caller.
+#[derive(Debug, PartialEq)]
+pub enum MinigrepError {
+    /// The query is missing from the arguments
+    MissingQuery,
+    /// No file to search in is given in the arguments
+    MissingFilename,
+    /// The file with this name can not be opened
+    FileNotFound(String),
+    /// The pattern file with this name can not be opened
+    PatternFileNotFound(String),
+    /// The path given is a directory but the search is not recursive
+    IsDirectory(String),
+    /// The standard input can not be read
+    StdinNotReadable,
+    /// The file with this name can be opened but not read
+    NotReadable(String),
+    /// The file with this name is not valid UTF-8
+    InvalidUtf8(String),
+    /// The file with this name is binary
+    Binary(String),
+    /// The result can not be written to the standard output
+    NotWritable,
+    /// The file with this name is compressed in this format but can not be decompressed
+    Decompression(String, &'static str),
+    /// This style argument can not be parsed as a `u8`
+    InvalidStyle(String),
+    /// This style is not one of `style::STYLES`
+    UnsupportedStyle(u8),
+    /// This flag expects a value but none is given
+    MissingValue(String),
+    /// The value given to this flag (first element) can not be parsed
+    InvalidValue(String, String),
+    /// This regular expression can not be compiled, for the reason given by the second element
+    InvalidRegex(String, String),
+    /// The colour can not be parsed, for this reason
+    InvalidColor(String),
+    /// This flag is not known
+    UnknownFlag(String),
+    /// A value is given to this flag, which does not expect one
+    UnexpectedValue(String),
+    /// The search lasted longer than this number of seconds
+    Timeout(u64),
+    /// These two flags can not be used together
+    ConflictingFlags(String, String),
+    /// This number of files could not be read while searching several ones
+    UnreadableFiles(usize),
+    /// The configuration file with this name is not valid, for the reason given by the second 
+    /// element
+    InvalidConfigFile(String, String),
+}
+
+impl fmt::Display for MinigrepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MinigrepError::MissingQuery => write!(f, "Missing the first argument (query)"),
+            MinigrepError::MissingFilename => write!(f, "Missing the second argument (filename)"),
+            MinigrepError::FileNotFound(name) => write!(f, "Could not open the file {}", name),
+            MinigrepError::PatternFileNotFound(name) => {
+                write!(f, "Could not open the pattern file {}", name)
+            },
+            MinigrepError::IsDirectory(name) => write!(f, "{} is a directory", name),
+            MinigrepError::StdinNotReadable => write!(f, "Could not read the standard input"),
+            MinigrepError::NotReadable(name) => write!(f, "Could not read the file {}", name),
+            MinigrepError::InvalidUtf8(name) => write!(f, "The file {} is not valid UTF-8", name),
+            MinigrepError::Binary(name) => write!(f, "The file {} is binary", name),
+            MinigrepError::NotWritable => write!(f, "Could not write to the standard output"),
+            MinigrepError::Decompression(name, format) => {
+                write!(f, "Could not decompress the file {} as {}", name, format)
+            },
+            MinigrepError::InvalidStyle(arg) => {
+                write!(f, "Could not parse the style argument {} as a u8", arg)
+            },
+            MinigrepError::UnsupportedStyle(style) => {
+                write!(f, "Unsupported style {} (expected 0 to 5 or 7 to 9)", style)
+            },
+            MinigrepError::MissingValue(flag) => write!(f, "Missing the value of {}", flag),
+            MinigrepError::InvalidValue(flag, value) => {
+                write!(f, "Invalid value {} for {}", value, flag)
+            },
+            MinigrepError::InvalidRegex(pattern, reason) => {
+                write!(f, "Invalid regular expression {}: {}", pattern, reason)
+            },
+            MinigrepError::InvalidColor(reason) => write!(f, "{}", reason),
+            MinigrepError::UnknownFlag(flag) => write!(f, "Unknown flag {}", flag),
+            MinigrepError::UnexpectedValue(flag) => write!(f, "The flag {} takes no value", flag),
+            MinigrepError::Timeout(1) => write!(f, "The search timed out after 1 second"),
+            MinigrepError::Timeout(n) => write!(f, "The search timed out after {} seconds", n),
+            MinigrepError::ConflictingFlags(first, second) => {
+                write!(f, "The flags {} and {} can not be used together", first, second)
+            },
+            MinigrepError::UnreadableFiles(1) => write!(f, "1 file could not be read"),
+            MinigrepError::UnreadableFiles(n) => write!(f, "{} files could not be read", n),
+            MinigrepError::InvalidConfigFile(name, reason) => {
+                write!(f, "Invalid configuration file {}: {}", name, reason)
+            },
+        }
+    }
+}
+
+impl Error for MinigrepError {}
@@ -0,0 +1,122 @@
+//! Rules of the `.gitignore` files, for the recursive search
+//!
+//! Only the common syntax is supported: blank lines and comments starting with `#`, negation
+//! with a leading `!`, patterns only matching directories with a trailing `/`, and the
+//! wildcards of `PathGlob`: `*` (any characters but `/`), `**` (any characters), `?` (any 
+//! character but `/`), and classes such as `[a-z]`. A pattern without `/` except at the end matches the names of the entries at any depth;
+//! one with another `/` matches their paths relative to the directory of the `.gitignore` file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::matcher::PathGlob;
+
+/// A rule of a `.gitignore` file
+#[derive(Debug)]
+struct Rule {
+    /// glob pattern, without the leading `!`, the leading `/`, and the trailing `/`
+    glob: PathGlob,
+    /// whether the rule re-includes the entries it matches
+    negated: bool,
+    /// whether the rule only applies to directories
+    dir_only: bool,
+    /// whether the pattern matches the path relative to the directory instead of the name
+    anchored: bool,
+}
+
+/// Rules of a `.gitignore` file, applying to the directory containing it
+#[derive(Debug)]
+pub struct Gitignore {
+    dir: PathBuf,
+    rules: Vec<Rule>,
+}
+
+impl Gitignore {
+    /// Read the `.gitignore` file of a directory, if there is one which can be read
+    pub fn read(dir: &Path) -> Option<Gitignore> {
+        let content = fs::read_to_string(dir.join(".gitignore")).ok()?;
+        Some(Gitignore::parse(dir, &content))
+    }
+
+    /// Parse the content of a `.gitignore` file in a directory
+    pub fn parse(dir: &Path, content: &str) -> Gitignore {
+        let mut rules = Vec::<Rule>::new();
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => (true, pattern),
+                None => (false, line),
+            };
+            let (dir_only, pattern) = match pattern.strip_suffix('/') {
+                Some(pattern) => (true, pattern),
+                None => (false, pattern),
+            };
+            let anchored = pattern.contains('/');
+            let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+            if pattern.is_empty() {
+                continue;
+            }
+            // as with git, a pattern which can not be compiled is skipped
+            if let Ok(glob) = PathGlob::new(pattern) {
+                rules.push(Rule { glob, negated, dir_only, anchored });
+            }
+        }
+        Gitignore { dir: dir.to_path_buf(), rules }
+    }
+
+    /// Whether the rules ignore (`Some(true)`) or re-include (`Some(false)`) a path, or do not
+    /// apply to it (`None`); the last matching rule wins
+    fn matches(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.dir).ok()?.to_str()?;
+        let name = path.file_name()?.to_str()?;
+        self.rules.iter()
+                  .rev()
+                  .filter(|rule| is_dir || !rule.dir_only)
+                  .find(|rule| rule.glob.matches(if rule.anchored { relative } else { name }))
+                  .map(|rule| !rule.negated)
+    }
+}
+
+/// Whether a path is ignored by the `.gitignore` files of the directories it is in
+///
+/// `gitignores` are ordered from the outermost directory to the innermost one, whose rules
+/// take precedence.
+pub fn is_ignored(gitignores: &[Gitignore], path: &Path, is_dir: bool) -> bool {
+    gitignores.iter()
+              .rev()
+              .find_map(|gitignore| gitignore.matches(path, is_dir))
+              .unwrap_or(false)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gitignore_rules() {
+        let gitignores = [Gitignore::parse(Path::new("repo"),
+                                           "# comment\ntarget/\n*.log\n!keep.log\n/src/gen\n")];
+        let ignored = |path: &str, is_dir: bool| is_ignored(&gitignores, Path::new(path), is_dir);
+        assert!(ignored("repo/target", true));
+        assert!(ignored("repo/sub/target", true));
+        assert!(!ignored("repo/target", false));
+        assert!(ignored("repo/sub/app.log", false));
+        assert!(!ignored("repo/keep.log", false));
+        assert!(ignored("repo/src/gen", false));
+        assert!(!ignored("repo/sub/src/gen", false));
+        assert!(!ignored("repo/main.rs", false));
+    }
+
+    #[test]
+    fn nested_gitignores() {
+        let gitignores = [Gitignore::parse(Path::new("repo"), "*.txt\n"),
+                          Gitignore::parse(Path::new("repo/sub"), "!notes.txt\n")];
+        assert!(is_ignored(&gitignores, Path::new("repo/sub/other.txt"), false));
+        assert!(!is_ignored(&gitignores, Path::new("repo/sub/notes.txt"), false));
+        assert!(is_ignored(&gitignores, Path::new("repo/notes.txt"), false));
+    }
+}
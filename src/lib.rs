@@ -9,238 +9,4050 @@
 //!
 //! ## Use
 //!
-//!     minigrep query filename [style]
+//! ```text
+//! minigrep [flags] [--] query filename... [style]
+//! minigrep [flags] -e query... [--] filename... [style]
+//! ```
 //! 
 //! * `query` (String): the string to search for
-//! * `filename` (String): the name of the file to search in
+//! * `-e query` (optional, may be repeated): a string to search for; lines containing any of 
+//!   them are selected, and all the positional arguments are then file names
+//! * `--any LIST` (optional, may be repeated): comma-separated list of strings to search for, 
+//!   with `\,` standing for a comma inside one of them; this is the same as giving each of them
+//!   with `-e`, so the positional query is then not read either
+//! * `filename` (String): the name of the file to search in, or `-` to read the standard input;
+//!   several files may be given, in which case each line is preceded by the file name
 //! * `style` (Integer, optional): the style the query is to be printed with
+//! * `-f FILE` (optional, may be repeated): read strings to search for from `FILE`, one per 
+//!   line, or from the standard input if `FILE` is `-`; empty lines are skipped, and all the 
+//!   positional arguments are then file names
+//! * `--style LIST` (optional): comma-separated list of styles the query is to be printed with,
+//!   such as `1,4` (bold and underline), instead of `style`
+//! * `--reverse-match` (flag, optional): highlight the matches with reverse video (style 7), 
+//!   which does not depend on the colours supported by the terminal; it takes precedence over 
+//!   the styles and over the colours given with `--color RGB`, `--color-match`, and 
+//!   `--highlight-bg`
+//! * `-n` (flag, optional): print the line number before each matching line
+//! * `-h`, `-H` (flags, optional): never or always print the file name before each line, 
+//!   whatever the number of files; if both are present, the last one wins
+//! * `-v` (flag, optional): print the lines which do not contain `query` instead
+//! * `-c` (flag, optional): only print the number of selected lines
+//! * `--rank` (flag, optional): only print the name of each file with its number of selected 
+//!   lines, from the largest number to the smallest (and by name for equal numbers), skipping the
+//!   files without any selected line
+//! * `--rank-all` (flag, optional): as `--rank`, including the files without any selected line
+//! * `--count-nonmatch` (flag, optional): only print the number of lines which do not contain 
+//!   `query`, as `-c -v`
+//! * `--count-per-line` (flag, optional): print the number of occurrences of `query` in each 
+//!   selected line before it, after the line number, byte offset, and column if any
+//! * `-B N`, `-A N`, `-C N` (optional): print `N` lines of context before, after, or around
+//!   each selected line
+//! * `--context-separator SEP` (optional): write the line `SEP` between the groups of lines
+//!   printed with `-B`, `-A`, or `-C` which are not contiguous (by default, `--`)
+//! * `--no-separator` (flag, optional): write nothing between the groups of context lines
+//! * `--no-merge-context` or `--window` (flag, optional): print each selected line in its own 
+//!   block of context, separated from the others by the context separator, instead of merging
+//!   the groups of lines which overlap
+//! * `--passthrough` (flag, optional): print all the lines, the selected ones with their 
+//!   matches highlighted
+//! * `--highlight-context` (flag, optional): dim the context lines, so that the selected ones
+//!   stand out
+//! * `-E`, `--regex` (flag, optional): interpret `query` as a regular expression
+//! * `-F`, `--fixed-strings` (flag, optional): match `query` literally, even with `-E` or 
+//!   `--glob`
+//! * `--glob` (flag, optional): interpret `query` as a glob pattern, where `*` matches any 
+//!   sequence of characters, `?` any single character, and `[...]` any of those between the 
+//!   brackets; it matches anywhere in the line unless anchored with `^` or `$`
+//! * `-w` (flag, optional): only match whole words
+//! * `-x` (flag, optional): only select the lines equal to `query` (or matched entirely by it 
+//!   with `-E`)
+//! * `--trim` (flag, optional): remove the whitespace at the start and end of `query`
+//! * `--normalize-whitespace` (flag, optional): consider any run of whitespace in `query` and in
+//!   the lines as a single space
+//! * `-b` (flag, optional): print the byte offset of the start of each line in its file (or of
+//!   each match with `-o`) after the line number
+//! * `--column` (flag, optional): print the column of the first match in each line, counted in 
+//!   characters, after the line number
+//! * `-o` (flag, optional): only print the matches, each on its own line
+//! * `--replace TEXT` (optional): print the selected lines with the matches replaced by `TEXT`
+//!   (highlighted instead of the matches); the files are not modified
+//! * `--byte-start N`, `--byte-end M` (optional): only search the lines which start at a byte 
+//!   offset from `N` (included) to `M` (excluded) in each file; a line starting before `N` is 
+//!   skipped even if it ends after it
+//! * `-m N` (optional): stop reading each file after `N` selected lines
+//! * `--json` (flag, optional): print one JSON object per selected line, with its number, text, 
+//!   and file, without highlight
+//! * `--csv` (flag, optional): print a header, then one CSV row per selected line, with its 
+//!   number and text (and file, if the file names are printed), without highlight
+//! * `--color RGB` (optional): colour of the matches, in hexadecimal format (`ff8800` or `f80`)
+//! * `--color-match RGB` (optional): the same as `--color RGB`
+//! * `--color-line RGB` (optional): colour of the rest of the selected lines (by default, that
+//!   of the terminal)
+//! * `--highlight-bg RGB` (optional): background colour of the matches, which can be combined 
+//!   with a foreground colour given with `--color RGB` or `--color-match RGB`
+//! * `--color WHEN` (optional): when to use colours and styles: `auto` (the default) if the 
+//!   standard output is a terminal, `always`, or `never`
+//! * `--color-mode MODE` (optional): how colours are written: `truecolor` (24-bit colours, the
+//!   default), `256` (nearest colour of the 256-colour palette), or `ansi` (nearest of the 16 
+//!   ANSI colours), for terminals which do not support 24-bit colours
+//! * `-i`, `-s` (flags, optional): perform a case-insensitive or case-sensitive search
+//! * `--smart-case` (flag, optional): perform a case-insensitive search unless `query` contains
+//!   an uppercase letter
+//! * `--total` (flag, optional): with `-c`, also print the total number of selected lines in all
+//!   the files, as `total:N` (implies `-c`)
+//! * `--progress` (flag, optional): write the percentage of each file read so far to the 
+//!   standard error, at most a few times per second, if it is a terminal
+//! * `--group-separator SEP` (optional): when several files are searched, write the line `SEP` 
+//!   between the lines printed for successive files (by default, an empty line)
+//! * `--no-group-separator` (flag, optional): write nothing between the lines of successive files
+//! * `--summary` (flag, optional): at the end, write the number of selected lines, files 
+//!   containing them, and lines read to the standard error
+//! * `--timeout N` (optional): stop the search with an error after `N` seconds
+//! * `-q` (flag, optional): print nothing and stop at the first selected line
+//! * `-Z` (flag, optional): end the file names with a NUL byte instead of `:` (or of a newline
+//!   with `-l` and `-L`)
+//! * `--line-null` (flag, optional): end the printed lines, including the context lines, the 
+//!   separators, the counts, the file names, the JSON and CSV rows, and the notices about binary
+//!   files, with a NUL byte instead of a newline; the prefixes are unchanged
+//! * `-l` (flag, optional): only print the names of the files containing a selected line
+//! * `-L` (flag, optional): only print the names of the files containing no selected line; 
+//!   files which are not valid UTF-8 are considered as such
+//! * `--files-from FILE` (optional): also search the files listed in `FILE`, one per line, or 
+//!   in the standard input if `FILE` is `-`; `filename` may then be omitted
+//! * `-r` (flag, optional): search the files in the directories given as `filename` and their
+//!   subdirectories; files which are not valid UTF-8 are skipped
+//! * `--max-depth N` (optional): with `-r`, only search the subdirectories up to `N` levels 
+//!   down, 0 meaning only the files directly in the directories given
+//! * `--follow` (flag, optional): with `-r`, follow the symbolic links to directories
+//! * `--respect-gitignore` (flag, optional): with `-r`, skip the files and directories ignored by
+//!   the `.gitignore` files, and the `.git` directories
+//! * `--include GLOB`, `--exclude GLOB` (optional, may be repeated): with `-r`, only search the 
+//!   files whose name matches one of the patterns given with `--include`, if any, and none of
+//!   those given with `--exclude`; the patterns support `*`, `?`, and classes such as `[a-z]`
+//! * `--name` (flag, optional): print the paths of the files whose name, without the 
+//!   directories, is selected, instead of searching their content; with `-r`, this is similar 
+//!   to `find | grep`
+//! * `--explain` or `--dry-run` (flag, optional): print the queries, the case mode, the styles, 
+//!   and the files to search, after the expansion of the directories, to the standard error, 
+//!   and exit without searching
+//! * `--binary-files MODE` (optional): how to search the binary files, *i.e.*, those with a 
+//!   NUL byte in their first 8 KiB: `binary` (the default) only prints whether they contain a 
+//!   selected line, `text` searches them as text, and `without-match` skips them; `-a` and `-I`
+//!   are short for `text` and `without-match`
+//! * `--threads N` (optional): search several files with `N` threads (by default, as many as 
+//!   logical CPUs); the output is the same as with a single one
+//! * `--field N` (optional): only search the field number `N` (starting from 1) of each line; 
+//!   the lines with fewer fields are not selected, and the whole lines are still printed
+//! * `--field-sep SEP` (optional): separator of the fields for `--field`, a tab by default
+//! * `--expand-tabs[=N]` (optional): print the tabs of the lines as spaces, up to the next 
+//!   multiple of `N` columns (8 by default) from the start of the line; the byte offsets and 
+//!   columns printed with `-b` and `--column` are still those of the original lines
+//! * `--max-line-length N` (optional): print at most `N` characters of each line, followed by 
+//!   `…` if it is longer; if the first match of a selected line would be cut off, the printed
+//!   characters are instead centred on it, with `…` before them
 //! 
 //! ## Behaviour
 //! 
 //! Print all lines in the file `filename` containing the string `query`. 
+//! If a file can not be read while searching several ones, an error is printed and the search
+//! goes on with the next file.
+//!
+//! Files whose name ends with `.gz` are decompressed on the fly, unless the default `gzip` 
+//! feature is disabled, and so are those whose name ends with `.bz2` or `.xz` with the `bzip2`
+//! or `xz` feature.
 //! 
-//! If the environment variable `CASE_INSENSITIVE` is set, the search is performed in a
-//! case-insensitive way.
+//! If the flag `-i` is present, or if the environment variable `CASE_INSENSITIVE` is set and 
+//! none of the flags `-s` and `--smart-case` is present, the search is performed in a 
+//! case-insensitive way. With `--smart-case`, it is case-insensitive for the queries without 
+//! uppercase letters and case-sensitive for the others. If several of `-i`, `-s`, and 
+//! `--smart-case` are present, the last one wins.
+//!
+//! If the environment variable `NO_COLOR` is set to a non-empty value, no colour or style is 
+//! used.
+//!
+//! Default flags may be set in a `.minigreprc` file in the current or home directory, as 
+//! `key = value` lines in a subset of TOML (`line-number = true`, `style = [1, 4]`); those given
+//! on the command line take precedence. The patterns can not be set in this file.
+//!
+//! ## Exit status
+//!
+//! As for `grep`, the exit status is 0 if a line is selected, 1 if none is, and 2 if an error 
+//! occurred (unless a line is selected with `-q`). With `-L`, it is 0 if a file name is printed
+//! and 1 otherwise.
 //!
 //! ## Example 
 //!
-//!     minigrep you poem.txt 1
+//! ```text
+//! minigrep you poem.txt 1
+//! ```
 
-use std::fs;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::num::NonZeroUsize;
 use std::env;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 use std::error::Error;
 
-mod style;
+pub mod contents;
+pub mod error;
+pub mod style;
+mod compression;
+mod config_file;
+mod gitignore;
+mod matcher;
+mod progress;
+mod walk;
 
+use contents::Contents;
+use error::MinigrepError;
+use matcher::{Matcher, PathGlob};
+use style::{Color, ColorChoice, ColorMode, Styler};
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    
-    // read the file
-    let contents = read_file(config.filename)?;
 
-    // select the lines that contain the query
-    let lines_with_query = if config.case_sensitive {
-        search(&config.query, &contents)
+/// Search the files and print the result according to the configuration
+///
+/// Return whether at least one line was selected or, with `files_without_match`, whether at 
+/// least one file was listed.
+///
+/// # Errors
+///
+/// When a single file is searched, failing to read it is an error, as is failing to read the 
+/// list of files given by `files_from`. If the search lasts longer than `timeout` seconds, it is
+/// stopped and `Timeout` is returned; the lines printed until then remain. When several files are, 
+/// those which can not be read are reported on the standard error and skipped, and 
+/// `UnreadableFiles` is returned at the end, unless a line was selected in quiet mode. Failing to
+/// write to the standard output stops the search with `NotWritable`.
+///
+/// The result is written to the standard output; see `run_to_writer` to write it elsewhere. 
+/// Unless it is a terminal, where the lines are written as they are found, the output is 
+/// buffered and flushed at the end, including when the search stops with an error.
+///
+/// Several files are searched in parallel with `threads` threads, or as many as logical CPUs if
+/// it is not set, except in quiet mode; the result of each file is written once those of the 
+/// previous ones are, so that the output does not depend on the number of threads.
+pub fn run(mut config: Config) -> Result<bool, Box<dyn Error>> {
+    let mut stdout = io::stdout().lock();
+    let is_terminal = stdout.is_terminal();
+    if is_terminal {
+        // with `ColorChoice::Auto`, the colours are then written
+        config.styler = Styler::new(config.color, config.color_mode, true);
+        return run_to_writer(config, &mut stdout);
+    }
+    let mut out = io::BufWriter::new(stdout);
+    let result = run_to_writer(config, &mut out);
+    // an error of the search takes precedence over one while flushing the rest of the output
+    match (result, out.flush()) {
+        (Err(e), _) => Err(e),
+        (Ok(_), Err(_)) => Err(Box::new(MinigrepError::NotWritable)),
+        (Ok(selected), Ok(())) => Ok(selected),
+    }
+}
+
+
+/// Search the files and write the result to `out`, according to the configuration
+///
+/// The result and the errors are those of `run`, which only differs by writing to the standard
+/// output. The errors and warnings about the files, the progress, and the summary are still 
+/// written to the standard error. As `out` is not known to be a terminal, `ColorChoice::Auto` 
+/// means no colours, whatever the standard output is.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::{run_to_writer, Config};
+///
+/// let config = Config::builder().query("frog").filename("poem.txt").build().unwrap();
+/// let mut out = Vec::<u8>::new();
+///
+/// assert!(run_to_writer(config, &mut out).unwrap());
+/// assert_eq!(b"How public, like a frog\n".to_vec(), out);
+/// ```
+pub fn run_to_writer(config: Config, out: &mut impl Write) -> Result<bool, Box<dyn Error>> {
+    let out: &mut dyn Write = out;
+
+    let patterns = all_patterns(&config)?;
+    let queries = trimmed_queries(&config, &patterns);
+    let matcher = build_matcher(&config, &queries)?;
+
+    // a timeout too large to be represented is no timeout
+    let deadline = config.timeout
+                         .and_then(|seconds| Instant::now().checked_add(Duration::from_secs(seconds)));
+    let state = SearchState::new(deadline);
+
+    let filenames = all_filenames(&config)?;
+
+    // with `--explain`, the search is only described
+    if config.explain {
+        eprint!("{}", explanation(&config, &queries, &list_inputs(&config, &filenames)));
+        return Ok(true);
+    }
+
+    // with `--rank`, the numbers of lines are sorted before being printed
+    if config.rank {
+        let inputs = list_inputs(&config, &filenames);
+        let (n_total, n_files) = rank_files(&config, &matcher, &inputs, &state, out)?;
+        print_summary(&config, n_total, n_files, &state);
+        return Ok(n_total > 0);
+    }
+
+    // with `--name`, the files are not read
+    if config.name {
+        let inputs = list_inputs(&config, &filenames);
+        let n_selected = search_names(&config, &matcher, &inputs, out)?;
+        print_summary(&config, n_selected, n_selected, &state);
+        return Ok(n_selected > 0);
+    }
+
+    // with a single file, failing to read it is an error
+    if config.files_from.is_none() && filenames.len() == 1 && !Path::new(&filenames[0]).is_dir() {
+        let filename = &filenames[0];
+        let prefix = if config.with_filename { Some(filename.as_str()) } else { None };
+        print_csv_header(&config, prefix.is_some(), out)?;
+        let n_selected = search_file(&config, &matcher, prefix, filename, &state, out)?;
+        print_total(&config, n_selected, out)?;
+        print_summary(&config, n_selected, usize::from(n_selected > 0), &state);
+        return Ok(n_selected > 0);
+    }
+
+    // otherwise, report the files which can not be read and go on with the others
+    let inputs = list_inputs(&config, &filenames);
+    print_csv_header(&config, !config.suppress_filename, out)?;
+    let separator = if config.count || config.files_with_matches || config.files_without_match 
+                       || config.json || config.csv {
+        None
     } else {
-        search_case_insensitive(&config.query, &contents)
+        config.group_separator.as_deref()
+    };
+    let mut n_total = 0;
+    let mut n_files = 0;
+    let mut n_errors = 0;
+    let mut report = |result: Result<usize, MinigrepError>| match result {
+        Ok(n_selected) => {
+            n_total += n_selected;
+            n_files += usize::from(n_selected > 0);
+            Ok(())
+        },
+        Err(e @ MinigrepError::Timeout(_)) | Err(e @ MinigrepError::NotWritable) => Err(e),
+        Err(e) => {
+            print_error(config.styler, &e);
+            n_errors += 1;
+            Ok(())
+        },
     };
 
-    // print the result
-    if config.style > 0 {
-        for n_line in lines_with_query {
-            println!("{}", format(&contents[n_line], &config.query, config.style));
+    // in quiet mode, the search stops at the first selected line, so it is not parallelized
+    let n_threads = config.threads.unwrap_or_else(default_threads).min(inputs.len());
+    if n_threads > 1 && !config.quiet {
+        let mut out = Separated::new(out, separator, line_terminator(&config));
+        search_parallel(&config, &matcher, &inputs, &state, n_threads, &mut out, &mut report)?;
+    } else {
+        let mut out = Separated::new(out, separator, line_terminator(&config));
+        for input in &inputs {
+            out.next_file();
+            let result = search_input(&config, &matcher, input, &state, &mut out);
+            let selected = matches!(result, Ok(n) if n > 0);
+            report(result)?;
+            if config.quiet && selected {
+                break;
+            }
+        }
+    }
+    print_total(&config, n_total, out)?;
+    print_summary(&config, n_total, n_files, &state);
+
+    if n_errors > 0 && !(config.quiet && n_total > 0) {
+        return Err(Box::new(MinigrepError::UnreadableFiles(n_errors)));
+    }
+    Ok(n_total > 0)
+}
+
+
+/// Select the lines of the files matching the configuration, without printing them
+///
+/// The patterns and the files are those `run` would search, including the patterns read with 
+/// `-f`, the file names read with `--files-from`, and the files found in the directories with 
+/// `-r`. The lines are selected as by `run`, including with `invert` and `max_count` (for 
+/// each file); the options about the output, such as the context or the styles, are ignored.
+/// Each match holds the name of its file and the byte ranges of the matches in the line, which
+/// are empty for inverted matches.
+///
+/// # Errors
+///
+/// * those of `Config::new` about the patterns, such as `InvalidRegex`
+/// * `FileNotFound` if a pattern file, the list of files, or a file to search can not be read
+/// * `IsDirectory` if a directory is given without the recursive flag
+/// * those of `search_reader` about reading the lines; the files found in a directory which are
+///   not valid UTF-8 are skipped
+///
+/// Unlike `run`, the first error stops the search.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::{collect_matches, Config, Match};
+///
+/// let config = Config::builder().query("frog").filename("poem.txt").build().unwrap();
+///
+/// assert_eq!(vec![Match { line_number: 7, text: "How public, like a frog".to_string(), 
+///                         file: Some("poem.txt".to_string()), spans: vec![(19, 23)] }], 
+///            collect_matches(&config).unwrap());
+/// ```
+pub fn collect_matches(config: &Config) -> Result<Vec<Match>, Box<dyn Error>> {
+    let patterns = all_patterns(config)?;
+    let matcher = build_matcher(config, &trimmed_queries(config, &patterns))?;
+    let mut matches = Vec::<Match>::new();
+    for input in list_inputs(config, &all_filenames(config)?) {
+        let (name, found) = match input {
+            Input::File(name) => (name, false),
+            Input::Found(name) => (name, true),
+            Input::Directory(name) => return Err(Box::new(MinigrepError::IsDirectory(name))),
+        };
+        let mut n_selected = 0;
+        for (n_line, line) in lines_with_offsets(open_input(&name, false, false)?, false)
+                                  .enumerate() {
+            if config.max_count.is_some_and(|max| n_selected >= max) {
+                break;
+            }
+            let line = match line {
+                Ok((_, line)) => line,
+                // files which are not text are skipped
+                Err(e) if e.kind() == io::ErrorKind::InvalidData && found => break,
+                Err(e) => return Err(Box::new(line_error(&name, &e))),
+            };
+            let spans = matcher.find(&line);
+            if spans.is_empty() != config.invert {
+                continue;
+            }
+            n_selected += 1;
+            matches.push(Match { line_number: n_line + 1, text: line, 
+                                 file: Some(name.to_string()), spans });
         }
+    }
+    Ok(matches)
+}
+
+
+/// Queries and patterns read from the pattern files, skipping their empty lines
+fn all_patterns(config: &Config) -> Result<Vec<String>, MinigrepError> {
+    let mut patterns = config.queries.clone();
+    for pattern_file in &config.pattern_files {
+        patterns.extend(read_patterns(pattern_file)?);
+    }
+    Ok(patterns)
+}
+
+
+/// Queries to search for, trimmed if `config.trim` is `true`
+fn trimmed_queries<'a>(config: &Config, patterns: &'a [String]) -> Vec<&'a str> {
+    patterns.iter()
+            .map(|query| if config.trim { query.trim() } else { query })
+            .collect()
+}
+
+
+/// Matcher selecting the lines containing any of the queries
+///
+/// # Errors
+///
+/// * `InvalidRegex` if a query is not a valid regular expression (or glob pattern)
+fn build_matcher(config: &Config, queries: &[&str]) -> Result<Matcher, MinigrepError> {
+    // a fixed string takes precedence over a glob pattern, which takes precedence over a regular
+    // expression; without any pattern (from an empty pattern file), no line matches
+    let matcher = if config.fixed || queries.is_empty() {
+        Matcher::literals(queries, config.case)
+    } else if config.glob {
+        Matcher::globs(queries, config.case)?
+    } else if config.regex {
+        Matcher::regexes(queries, config.case)?
     } else {
-        for n_line in lines_with_query {
-            println!("{}", &contents[n_line]);
+        Matcher::literals(queries, config.case)
+    };
+    Ok(matcher.whole_words(config.word)?.whole_line(config.line_match)?
+              .normalize_whitespace(config.normalize_whitespace)
+              .field(config.field.map(|n| (config.field_separator.as_str(), n))))
+}
+
+
+/// Files given directly and files listed in the file `config.files_from`, skipping its empty 
+/// lines
+fn all_filenames(config: &Config) -> Result<Vec<String>, MinigrepError> {
+    let mut filenames = config.filenames.clone();
+    if let Some(list) = &config.files_from {
+        let list = read_file(list)?;
+        filenames.extend(list.lines().into_iter().filter(|name| !name.is_empty()).map(String::from));
+    }
+    Ok(filenames)
+}
+
+
+/// State shared by the searches of all the files, including those in other threads
+#[derive(Debug)]
+struct SearchState {
+    /// time after which the search stops, if any
+    deadline: Option<Instant>,
+    /// number of lines read so far
+    lines_scanned: AtomicUsize,
+}
+
+impl SearchState {
+    fn new(deadline: Option<Instant>) -> SearchState {
+        SearchState { deadline, lines_scanned: AtomicUsize::new(0) }
+    }
+}
+
+
+/// Blocks of context printed with `no_merge_context`, one for each selected line
+///
+/// Each block holds the `before` lines preceding its selected line, the line itself, and the 
+/// `after` lines following it, whether they are selected or not, so that the blocks of close 
+/// lines overlap. Each line of the file appears once in a block. The lines are given already 
+/// rendered, and each block is written once complete, with the separator, if any, between two
+/// blocks; the separator includes its line terminator.
+struct Windows<'a> {
+    before: usize,
+    after: usize,
+    separator: Option<&'a str>,
+    /// last lines read, up to `before`
+    recent: VecDeque<Vec<u8>>,
+    /// blocks not complete yet, with the number of lines they still need
+    blocks: VecDeque<(Vec<u8>, usize)>,
+    written: bool,
+}
+
+impl<'a> Windows<'a> {
+    fn new(before: usize, after: usize, separator: Option<&'a str>) -> Windows<'a> {
+        Windows { 
+            before, 
+            after, 
+            separator, 
+            recent: VecDeque::with_capacity(before), 
+            blocks: VecDeque::new(), 
+            written: false,
         }
     }
 
-    Ok(())
+    /// Whether no block is waiting for more lines
+    fn is_idle(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Add the next line, starting a new block if it is selected, and write the blocks which 
+    /// are then complete
+    fn push(&mut self, line: Vec<u8>, selected: bool, out: &mut dyn Write) 
+        -> Result<(), MinigrepError> {
+        for (block, left) in self.blocks.iter_mut().filter(|(_, left)| *left > 0) {
+            block.extend_from_slice(&line);
+            *left -= 1;
+        }
+        if selected {
+            let mut block = self.recent.iter().flatten().copied().collect::<Vec<u8>>();
+            block.extend_from_slice(&line);
+            self.blocks.push_back((block, self.after));
+        }
+        if self.before > 0 {
+            if self.recent.len() == self.before {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(line);
+        }
+        // the blocks are complete in the order they were started
+        while self.blocks.front().is_some_and(|(_, left)| *left == 0) {
+            let (block, _) = self.blocks.pop_front().unwrap();
+            self.write(&block, out)?;
+        }
+        Ok(())
+    }
+
+    /// Write the remaining blocks, at the end of the file
+    fn finish(&mut self, out: &mut dyn Write) -> Result<(), MinigrepError> {
+        while let Some((block, _)) = self.blocks.pop_front() {
+            self.write(&block, out)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, block: &[u8], out: &mut dyn Write) -> Result<(), MinigrepError> {
+        if let (true, Some(separator)) = (self.written, self.separator) {
+            out.write_all(separator.as_bytes()).map_err(not_writable)?;
+        }
+        self.written = true;
+        out.write_all(block).map_err(not_writable)
+    }
 }
 
 
+/// A file to search when several are given
 #[derive(Debug, PartialEq)]
-pub struct Config {
-    query: String,
-    filename: String,
-    style: u8,
-    case_sensitive: bool,
+enum Input {
+    /// a file given directly
+    File(String),
+    /// a file found in a directory; it is skipped if it is not valid UTF-8
+    Found(String),
+    /// a directory given without the recursive flag
+    Directory(String),
 }
 
 
-impl Config {
-    /// Create a new Config from an array of arguments
-    /// 
-    /// # Argument
-    ///
-    /// `args`: array of `String` with at least 3 elements
-    ///
-    /// # Errors
-    ///
-    /// * `Not enough arguments` if the number of arguments is smaller than 2
-    ///
-    /// # Warnings
-    ///
-    /// * `Too many arguments` if the number of arguments is larger than 3
-    ///
-    /// # Values
-    ///
-    /// * `query` and `filemane` are given by the first two arguments.
-    /// * If there is a third argument, it is conerted to a `u8` and set to `style`. If not, 
-    /// `style` takes the value 0.
-    /// * `case_sensitive` is set to `true` if the environment variable `CASE_INSENSITIVE` is not 
-    /// set and to `false` if it is set.
-    pub fn new(mut args: env::Args) -> Result<Config, String> {
-    
-        // read the arguments
-        args.next();
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => return Err(style::add_fg("Missing the first argument (query)".to_string(), 
-                                             255, 0, 0))
-        };
-        let filename = match args.next() {
-            Some(arg) => arg,
-            None => return Err(style::add_fg("Missing the second argument (filename)".to_string(), 
-                                             255, 0, 0))
-        };
-        let style: u8 = match args.next() {
-            Some(arg) => {
-                let mut s = 0;
-                match arg.parse::<u8>() {
-                    Ok(x) => s = x,
-                    Err(_) => {
-                        eprintln!("{}",
-                            style::add_fg("WARNING: Could not parse the third argument (style) as a u8"
-                                          .to_string(), 
-                                          255, 255, 0));
-                    }
-                };
-                match args.next() {
-                    Some(_) => eprintln!("{}",
-                                style::add_fg(
-                                    "WARNING: Too many arguments; the 4th one and up will be discarded"
-                                    .to_string(), 
-                                    255, 255, 0)),
-                    None => ()
-                };
-                s
-            },
-            None => 0
+/// List the files to search, descending into the directories if the search is recursive
+fn list_inputs(config: &Config, filenames: &[String]) -> Vec<Input> {
+    // the patterns have been checked by `ConfigBuilder::build`
+    let globs = |patterns: &[String]| {
+        patterns.iter()
+                .filter_map(|pattern| PathGlob::new(pattern).ok())
+                .collect::<Vec<PathGlob>>()
+    };
+    let walk_options = walk::Options { max_depth: config.max_depth, follow: config.follow, 
+                                       gitignore: config.respect_gitignore, 
+                                       include: globs(&config.include), 
+                                       exclude: globs(&config.exclude), 
+                                       styler: config.styler };
+    let mut inputs = Vec::<Input>::new();
+    for filename in filenames {
+        if !Path::new(filename).is_dir() {
+            inputs.push(Input::File(filename.to_string()));
+        } else if config.recursive {
+            inputs.extend(walk::list_files(Path::new(filename), &walk_options)
+                              .iter()
+                              .map(|path| Input::Found(path.display().to_string())));
+        } else {
+            inputs.push(Input::Directory(filename.to_string()));
+        }
+    }
+    inputs
+}
+
+
+/// Search one of several files, writing the result to `out`
+///
+/// The file name is written before each line unless `config.suppress_filename` is `true`. 
+///
+/// # Errors
+///
+/// * `IsDirectory` if the input is a directory
+/// * those of `search_file`, except `InvalidUtf8` for a file found in a directory
+fn search_input(config: &Config, matcher: &Matcher, input: &Input, state: &SearchState, 
+                out: &mut dyn Write) -> Result<usize, MinigrepError> {
+    let name = match input {
+        Input::File(name) | Input::Found(name) => name,
+        Input::Directory(name) => return Err(MinigrepError::IsDirectory(name.to_string())),
+    };
+    let prefix = if config.suppress_filename { None } else { Some(name.as_str()) };
+    match (search_file(config, matcher, prefix, name, state, out), input) {
+        // files which are not text are skipped
+        (Err(MinigrepError::InvalidUtf8(_)), Input::Found(_)) => Ok(0),
+        (result, _) => result,
+    }
+}
+
+
+/// Human-readable description of a search, as printed by `--explain`
+///
+/// The labels are coloured according to the colour choice.
+fn explanation(config: &Config, queries: &[&str], inputs: &[Input]) -> String {
+    let label = |label: &str| config.styler.add_fg_named(label.to_string(), Color::Cyan);
+    let kind = if config.fixed {
+        "fixed strings"
+    } else if config.glob {
+        "glob patterns"
+    } else if config.regex {
+        "regular expressions"
+    } else {
+        "fixed strings"
+    };
+    let kind = if config.name {
+        format!("{}, matched against the names of the files", kind)
+    } else {
+        kind.to_string()
+    };
+    let case = match config.case {
+        CaseMode::Sensitive => "sensitive",
+        CaseMode::Insensitive => "insensitive",
+        CaseMode::Smart => "smart (insensitive unless a query contains an uppercase letter)",
+    };
+    let styles = if config.styles.is_empty() {
+        "none".to_string()
+    } else {
+        config.styles.iter().map(|style| style.to_string()).collect::<Vec<String>>().join(", ")
+    };
+    let mut res = format!("{} {}\n", label("Queries:"), 
+                          queries.iter().map(|query| json_string(query))
+                                 .collect::<Vec<String>>().join(", "));
+    res += &format!("{} {}\n", label("Kind:"), kind);
+    res += &format!("{} {}\n", label("Case:"), case);
+    res += &format!("{} {}\n", label("Styles:"), styles);
+    if let Some((r, g, b)) = config.highlight_color {
+        res += &format!("{} #{:02x}{:02x}{:02x}\n", label("Colour:"), r, g, b);
+    }
+    res += &format!("{} {}\n", label("Files:"), inputs.len());
+    for input in inputs {
+        res += &match input {
+            Input::File(name) | Input::Found(name) => format!("  {}\n", name),
+            Input::Directory(name) => format!("  {} (directory, skipped without -r)\n", name),
         };
+    }
+    res
+}
+
 
-        // set the case_sensitive value
-        let case_sensitive = env::var("CASE_INSENSITIVE").is_err();
+/// Print the names of the files with their numbers of selected lines, from the largest number
+/// to the smallest
+///
+/// The files with the same number are sorted by name, and those without any selected line are
+/// skipped unless `config.rank_all` is `true`. The numbers are highlighted as the matches. 
+/// Return the total number of selected lines and the number of files containing some.
+///
+/// # Errors
+///
+/// * `NotWritable` if the result can not be written to `out`
+/// * `Timeout` if the search takes longer than `config.timeout`
+/// * `UnreadableFiles` if some files can not be searched; they are reported on the standard 
+///   error and the others are still ranked
+fn rank_files(config: &Config, matcher: &Matcher, inputs: &[Input], state: &SearchState, 
+              out: &mut dyn Write) -> Result<(usize, usize), MinigrepError> {
+    let mut counts = Vec::<(usize, &str)>::with_capacity(inputs.len());
+    let mut n_errors = 0;
+    for input in inputs {
+        let name = match input {
+            Input::File(name) | Input::Found(name) | Input::Directory(name) => name.as_str(),
+        };
+        match search_input(config, matcher, input, state, &mut io::sink()) {
+            Ok(n_selected) => counts.push((n_selected, name)),
+            Err(e @ MinigrepError::Timeout(_)) => return Err(e),
+            Err(e) => {
+                print_error(config.styler, &e);
+                n_errors += 1;
+            },
+        }
+    }
+    counts.sort_by(|(n1, name1), (n2, name2)| n2.cmp(n1).then(name1.cmp(name2)));
 
-        Ok(Config { query, filename, style, case_sensitive })
+    let highlight = config.styles.iter().any(|&style| style > 0) 
+                    || config.highlight_color.is_some() || config.highlight_bg.is_some();
+    for &(n_selected, name) in counts.iter().filter(|(n, _)| *n > 0 || config.rank_all) {
+        let count = n_selected.to_string();
+        let count = if highlight {
+            highlight_line(config.styler, &count, &[(0, count.len())], &config.styles, config.highlight_color, 
+                           config.highlight_bg, None)
+        } else {
+            count
+        };
+        write!(out, "{}{}{}{}", config.styler.add_fg_named(name.to_string(), Color::Magenta),
+               if config.null { '\0' } else { ':' }, count, line_terminator(config))
+            .map_err(not_writable)?;
+    }
+    if n_errors > 0 {
+        return Err(MinigrepError::UnreadableFiles(n_errors));
     }
+    Ok((counts.iter().map(|(n, _)| n).sum(), counts.iter().filter(|(n, _)| *n > 0).count()))
 }
 
 
-/// Read the content of a file as a vector of strings, each element being a line in the file
+/// Print the paths of the files whose name is selected, returning their number
+///
+/// Only the last component of each path is matched, so that the directories do not select all 
+/// the files in them. The lines are otherwise selected as by `search_reader`, except for the 
+/// options which do not apply to file names, such as the context. In quiet mode, the search 
+/// stops at the first selected name and nothing is printed.
 ///
 /// # Errors
 ///
-/// * `Could not open the file` if the file can not be opened
-fn read_file(filename: String) -> Result<Vec<String>, String> {
-    let content = match fs::read_to_string(&filename) {
-        Ok(f) => f,
-        Err(_) => {
-            let err_message = style::add_fg(
-                format!("Could not open the file {}", &filename), 
-                255, 0, 0
-            );
-            return Err(err_message);
+/// * `NotWritable` if the result can not be written to `out`
+/// * `UnreadableFiles` if some of the inputs are directories given without the recursive flag;
+///   the names of the other files are still printed
+fn search_names(config: &Config, matcher: &Matcher, inputs: &[Input], out: &mut dyn Write) 
+    -> Result<usize, MinigrepError> {
+    let mut n_selected = 0;
+    let mut n_errors = 0;
+    for input in inputs {
+        let path = match input {
+            Input::File(path) | Input::Found(path) => path,
+            Input::Directory(name) => {
+                print_error(config.styler, &MinigrepError::IsDirectory(name.to_string()));
+                n_errors += 1;
+                continue;
+            },
+        };
+        let name = Path::new(path).file_name().map_or_else(|| path.into(), |n| n.to_string_lossy());
+        if matcher.find(&name).is_empty() != config.invert {
+            continue;
         }
-    };
-    let lines = content.split("\n").collect::<Vec<&str>>();
-    let mut res = Vec::<String>::new();
-    for line in lines {
-        res.push(line.to_string());
+        n_selected += 1;
+        if config.quiet {
+            break;
+        }
+        print_filename(config, path, out)?;
+    }
+    if n_errors > 0 && !(config.quiet && n_selected > 0) {
+        return Err(MinigrepError::UnreadableFiles(n_errors));
     }
-    Ok(res)
+    Ok(n_selected)
 }
 
-/// Format a string to highlight each occurrence of a word
+
+/// Search several files with `n_threads` worker threads
 ///
-/// # Examples
+/// The result of each file is kept in memory until those of the previous files are written, so
+/// that the output written to `out` is the same as for a sequential search. `report` is then 
+/// called with the result of each file, in order; the search stops at the first error it 
+/// returns.
 ///
-/// ```
-/// use minigrep::format; 
+/// # Errors
 ///
-/// let line = "This is a fine sentence!";
-/// let word = "fine";
-/// let style = 2;
-/// 
-/// let formatted_line = format(line, word, style);
+/// * `NotWritable` if the result can not be written to `out`
+/// * those returned by `report`
+fn search_parallel(config: &Config, matcher: &Matcher, inputs: &[Input], state: &SearchState,
+                   n_threads: usize, out: &mut Separated<'_>,
+                   report: &mut impl FnMut(Result<usize, MinigrepError>) -> Result<(), MinigrepError>)
+                   -> Result<(), MinigrepError> {
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel::<(usize, Vec<u8>, Result<usize, MinigrepError>)>();
+    thread::scope(|scope| {
+        for _ in 0..n_threads {
+            let sender = sender.clone();
+            let (next, stop) = (&next, &stop);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= inputs.len() {
+                        break;
+                    }
+                    let mut buffer = Vec::<u8>::new();
+                    let result = search_input(config, matcher, &inputs[i], state, &mut buffer);
+                    if sender.send((i, buffer, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        // write the results in the order of the inputs
+        let mut pending = HashMap::<usize, (Vec<u8>, Result<usize, MinigrepError>)>::new();
+        let mut n_written = 0;
+        for (i, buffer, result) in receiver {
+            pending.insert(i, (buffer, result));
+            while let Some((buffer, result)) = pending.remove(&n_written) {
+                n_written += 1;
+                out.next_file();
+                let written = out.write_all(&buffer).map_err(not_writable).and_then(|_| report(result));
+                if let Err(e) = written {
+                    stop.store(true, Ordering::Relaxed);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+
+/// Writer adding a separator line between the outputs of successive files
 ///
-/// assert_eq!("This is a \x1b[2;1mfine\x1b[0m sentence!".to_string(), 
-///            formatted_line)
-/// ```
-pub fn format(line: &str, word: &str, style: u8) -> String {
-    let line_s = &line.to_string();
-    let word_format =  style::add_style(word.to_string(), style);
-    str::replace(line_s, &word, &word_format)
+/// The separator is only written before the output of a file if something was written for the
+/// previous ones, so that there is none before the first output or after the last one. It is 
+/// followed by the same terminator as the printed lines.
+struct Separated<'a> {
+    out: &'a mut dyn Write,
+    separator: Option<&'a str>,
+    terminator: char,
+    // whether something was written, and whether the separator is due before the next write
+    written: bool,
+    pending: bool,
 }
 
+impl<'a> Separated<'a> {
+    fn new(out: &'a mut dyn Write, separator: Option<&'a str>, terminator: char) 
+        -> Separated<'a> {
+        Separated { out, separator, terminator, written: false, pending: false }
+    }
+
+    /// Start the output of the next file
+    fn next_file(&mut self) {
+        self.pending = self.written;
+    }
+}
 
-/// Select the indices of the strings containing the query
-fn search(query: &String, contents: &Vec<String>) -> Vec<usize>{
-    let mut res = Vec::<usize>::new();
-    for i in 0..contents.len() {
-        if contents[i].contains(query) {
-            res.push(i);
+impl Write for Separated<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let (true, Some(separator)) = (self.pending, self.separator) {
+            write!(self.out, "{}{}", separator, self.terminator)?;
         }
+        self.pending = false;
+        self.written = true;
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
     }
-    res
 }
 
 
-/// Select the indices of the strings containing the query, case-insensitive
-fn search_case_insensitive(query: &String, contents: &Vec<String>) -> Vec<usize>{
-    let mut res = Vec::<usize>::new();
-    let query = query.to_lowercase();
-    for i in 0..contents.len() {
-        if contents[i].to_lowercase().contains(&query) {
-            res.push(i);
-        }
+/// Default number of threads: the number of logical CPUs, or 1 if it can not be known
+fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+
+/// Write the total number of selected lines if requested
+fn print_total(config: &Config, n_total: usize, out: &mut dyn Write) -> Result<(), MinigrepError> {
+    if config.count && config.total && !config.quiet && !config.files_with_matches 
+        && !config.files_without_match {
+        write!(out, "total:{}{}", n_total, line_terminator(config)).map_err(not_writable)?;
     }
-    res
+    Ok(())
 }
 
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Write the number of selected lines, of files containing them, and of lines read to the 
+/// standard error if requested, except in quiet mode
+fn print_summary(config: &Config, n_total: usize, n_files: usize, state: &SearchState) {
+    if config.summary && !config.quiet {
+        let n_lines = state.lines_scanned.load(Ordering::Relaxed);
+        let plural = |n: usize, one: &str, several: &str| {
+            format!("{} {}", n, if n == 1 { one } else { several })
+        };
+        let summary = format!("{} in {} ({} scanned)", plural(n_total, "match", "matches"),
+                              plural(n_files, "file", "files"), plural(n_lines, "line", "lines"));
+        let (r, g, b) = Color::Cyan.rgb();
+        eprintln!("{}", config.styler.add_fg(summary, r, g, b));
+    }
+}
 
-    #[test]
-    fn format_1() {
-        let sentence = "I love blue cheese!";
-        let word = "love";
-        let style: u8 = 1;
-        let sentence_highlighted = format(sentence, word, 1);
-        let expected_result = "I \x1b[1;1mlove\x1b[0m blue cheese!".to_string();
-        assert_eq!(expected_result, sentence_highlighted);
+
+/// Read the non-empty lines of a pattern file, or of the standard input if `filename` is `-`
+///
+/// # Errors
+///
+/// * `PatternFileNotFound` if the file can not be opened
+/// * those of `read_file`
+fn read_patterns(filename: &str) -> Result<Vec<String>, MinigrepError> {
+    match read_file(filename) {
+        Ok(contents) => Ok(contents.lines().into_iter()
+                                   .filter(|pattern| !pattern.is_empty())
+                                   .map(String::from)
+                                   .collect()),
+        Err(MinigrepError::FileNotFound(name)) => Err(MinigrepError::PatternFileNotFound(name)),
+        Err(e) => Err(e),
     }
+}
 
-    #[test]
-    fn search_1() {
-        let query = "duct".to_string();
-        let contents = vec!["Rust:".to_string(),
-                            "safe, fast, productive.".to_string(), 
-                            "Pick three.".to_string(),
-                            "‘Ductape’ is a typo".to_string()];
-        assert_eq!(vec![1], search(&query, &contents));
+
+/// Print an error in red to the standard error
+fn print_error(styler: Styler, e: &MinigrepError) {
+    eprintln!("{}", styler.add_fg_named(e.to_string(), Color::Red));
+}
+
+
+/// Error for a failed write to the output
+fn not_writable(_: io::Error) -> MinigrepError {
+    MinigrepError::NotWritable
+}
+
+
+/// Search a file, or the standard input if `name` is `-`
+///
+/// The arguments and the result are as for `search_reader`, except with `files_without_match`:
+/// the name of the file is then printed if no line is selected in it or if it is not valid 
+/// UTF-8, and the number of files listed (0 or 1) is returned.
+///
+/// # Errors
+///
+/// * `FileNotFound` if the file can not be opened
+/// * those of `search_reader`, except `InvalidUtf8` with `files_without_match`
+fn search_file(config: &Config, matcher: &Matcher, filename: Option<&str>, name: &str, 
+               state: &SearchState, out: &mut dyn Write) -> Result<usize, MinigrepError> {
+    let escapes = style::color_enabled() && config.color != ColorChoice::Never;
+    let result = match open_input(name, config.progress, escapes) {
+        Ok(reader) => search_reader(config, matcher, filename, name, reader, state, out),
+        Err(e) => Err(e),
+    };
+    if !config.files_without_match {
+        return result;
     }
+    match result {
+        Ok(0) | Err(MinigrepError::InvalidUtf8(_)) => {
+            if !config.quiet {
+                print_filename(config, name, out)?;
+            }
+            Ok(1)
+        },
+        Ok(_) => Ok(0),
+        Err(e) => Err(e),
+    }
+}
 
-    #[test]
-    fn search_2() {
-        let query = "duct".to_string();
-        let contents = vec!["Rust:".to_string(),
-                            "safe, fast, productive.".to_string(), 
-                            "Pick three.".to_string(),
-                            "‘Ductape’ is a typo".to_string()];
-        assert_eq!(vec![1,3], search_case_insensitive(&query, &contents));
+
+/// Open a file for reading, or the standard input if `filename` is `-`
+///
+/// Compressed files are decompressed on the fly. If `progress` is `true`, the percentage of the
+/// file read so far is written to the standard error while it is read, unless its size is 
+/// unknown (as for the standard input) or the standard error is not a terminal; the progress 
+/// line is only cleared with an escape sequence if `escapes` is `true`.
+///
+/// # Errors
+///
+/// * `FileNotFound` if the file can not be opened
+fn open_input(filename: &str, progress: bool, escapes: bool) 
+    -> Result<Box<dyn BufRead>, MinigrepError> {
+    if filename == "-" {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file = match File::open(filename) {
+        Ok(file) => file,
+        Err(_) => return Err(MinigrepError::FileNotFound(filename.to_string())),
+    };
+    match file.metadata() {
+        Ok(metadata) if progress && metadata.is_file() && io::stderr().is_terminal() => {
+            let file = progress::Progress::new(file, metadata.len(), filename, io::stderr(), 
+                                               escapes);
+            Ok(compression::reader(filename, file))
+        },
+        _ => Ok(compression::reader(filename, file)),
+    }
+}
+
+
+/// Search the lines given by a reader and write the result to `out` as they are found
+///
+/// The number of lines read is added to `state.lines_scanned`, and the search stops at 
+/// `state.deadline`, if any.
+///
+/// Only the lines starting from `config.byte_start` and before `config.byte_end` are searched
+/// and printed, even as context; their numbers are still counted from the start of the reader.
+///
+/// If `filename` is not `None`, it is printed before each line. `name` is only used in the 
+/// errors, in the JSON output, and with `files_with_matches`. With `files_with_matches` or 
+/// `files_without_match`, the search stops at the first selected line. The number of selected
+/// lines is returned.
+///
+/// # Errors
+///
+/// * `InvalidUtf8` if a line is not valid UTF-8
+/// * `Decompression` if the reader fails while decompressing a compressed file
+/// * `NotReadable` if the reader fails for another reason
+/// * `NotWritable` if the result can not be written to `out`
+/// * `Timeout` if a line is read after the deadline, if any; as reading a line can block, the 
+///   search may go on for some time after it
+///
+/// If the reader is binary (see `is_binary`), it is considered as containing no selected line 
+/// with `BinaryMode::WithoutMatch`; with `BinaryMode::Binary`, it is searched without checking 
+/// that it is valid UTF-8, and only `Binary file NAME matches` is printed instead of the lines.
+fn search_reader(config: &Config, matcher: &Matcher, filename: Option<&str>, name: &str, 
+                 mut reader: impl BufRead, state: &SearchState, out: &mut dyn Write) 
+                 -> Result<usize, MinigrepError> {
+    let binary = config.binary_mode != BinaryMode::Text 
+                 && reader.fill_buf().is_ok_and(is_binary);
+    if binary && config.binary_mode == BinaryMode::WithoutMatch {
+        return Ok(0);
+    }
+    let hide_lines = binary && !config.count && !config.quiet && !config.files_with_matches 
+                     && !config.files_without_match;
+    let highlight = (config.styles.iter().any(|&style| style > 0) 
+                     || config.highlight_color.is_some() || config.highlight_bg.is_some() 
+                     || config.line_color.is_some()) 
+                    && !config.invert;
+    let prints_lines = !config.count && !config.json && !config.csv && !config.only_matching 
+        && !config.quiet && !config.files_with_matches && !config.files_without_match 
+        && !hide_lines;
+    // all the lines are printed with `passthrough`, so there is no context
+    let passthrough = config.passthrough && prints_lines;
+    let has_context = (config.before > 0 || config.after > 0) && prints_lines && !passthrough;
+    let separator = config.context_separator
+                          .as_ref()
+                          .map(|separator| format!("{}{}", separator, line_terminator(config)));
+    let mut windows = if has_context && config.no_merge_context {
+        Some(Windows::new(config.before, config.after, separator.as_deref()))
+    } else {
+        None
+    };
+    let mut n_selected = 0;
+
+    // lines kept to be printed before the next selected one
+    let mut before = VecDeque::<(usize, usize, String)>::with_capacity(config.before);
+    // number of lines still to be printed after the last selected one
+    let mut after_left = 0;
+    let mut last_printed: Option<usize> = None;
+    let mut n_scanned = 0;
+
+    for (n_line, line) in lines_with_offsets(reader, binary).enumerate() {
+
+        // stop after the maximum number of selected lines and their context
+        let max_reached = config.max_count.is_some_and(|max| n_selected >= max);
+        if max_reached && after_left == 0 && windows.as_ref().is_none_or(Windows::is_idle) {
+            break;
+        }
+
+        if state.deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            return Err(MinigrepError::Timeout(config.timeout.unwrap_or(0)));
+        }
+
+        let (offset, line) = match line {
+            Ok(l) => l,
+            Err(e) => return Err(line_error(name, &e)),
+        };
+        n_scanned += 1;
+
+        // only the lines starting in the byte range are searched
+        if offset < config.byte_start {
+            continue;
+        }
+        if config.byte_end.is_some_and(|end| offset >= end) {
+            break;
+        }
+
+        // select the line if it contains the query (or, if inverted, if it does not)
+        let spans = matcher.find(&line);
+        if max_reached || spans.is_empty() != config.invert {
+            if passthrough {
+                print_line(config, filename, n_line, offset, None, None, &expanded(config, &line), 
+                           '-', out)?;
+                continue;
+            }
+            if !has_context {
+                continue;
+            }
+            if let Some(windows) = &mut windows {
+                let mut rendered = Vec::<u8>::new();
+                print_context(config, filename, n_line, offset, &line, &mut rendered)?;
+                windows.push(rendered, false, out)?;
+                continue;
+            }
+            if after_left > 0 {
+                print_context(config, filename, n_line, offset, &line, out)?;
+                last_printed = Some(n_line);
+                after_left -= 1;
+            } else if config.before > 0 {
+                if before.len() == config.before {
+                    before.pop_front();
+                }
+                before.push_back((n_line, offset, line));
+            }
+            continue;
+        }
+        n_selected += 1;
+        if config.quiet || config.files_with_matches || config.files_without_match {
+            break;
+        }
+        if hide_lines {
+            write!(out, "Binary file {} matches{}", name, line_terminator(config))
+                .map_err(not_writable)?;
+            break;
+        }
+        if config.count {
+            continue;
+        }
+        if config.only_matching {
+            for &(start, end) in spans.iter().filter(|(start, end)| start < end) {
+                let piece = config.replace.as_deref().unwrap_or(&line[start..end]);
+                let column = Some(char_column(&line, start));
+                if config.json {
+                    write!(out, "{}{}", json_line(name, n_line, piece), line_terminator(config))
+                        .map_err(not_writable)?;
+                } else if config.csv {
+                    write!(out, "{}{}", csv_line(filename, n_line, piece), line_terminator(config))
+                        .map_err(not_writable)?;
+                } else if highlight {
+                    let piece = highlight_line(config.styler, piece, &[(0, piece.len())], &config.styles, 
+                                               config.highlight_color, config.highlight_bg, None);
+                    print_line(config, filename, n_line, offset + start, column, None, &piece, ':', 
+                               out)?;
+                } else {
+                    print_line(config, filename, n_line, offset + start, column, None, piece, ':', 
+                               out)?;
+                }
+            }
+            continue;
+        }
+
+        // substitute the replacement for the matches; the spans are then those of the 
+        // replacements, so that they are highlighted instead
+        let column = spans.first().map(|&(start, _)| char_column(&line, start));
+        let (line, spans) = match &config.replace {
+            Some(replacement) => replace_spans(&line, &spans, replacement),
+            None => (line, spans),
+        };
+        if config.json {
+            write!(out, "{}{}", json_line(name, n_line, &line), line_terminator(config))
+                .map_err(not_writable)?;
+            continue;
+        }
+        if config.csv {
+            write!(out, "{}{}", csv_line(filename, n_line, &line), line_terminator(config))
+                .map_err(not_writable)?;
+            continue;
+        }
+
+        let n_occurrences = spans.iter().filter(|(start, end)| start < end).count();
+        let (line, spans) = match config.tab_width {
+            Some(width) => expand_tabs(&line, &spans, width),
+            None => (line, spans),
+        };
+        let (line, spans) = match config.max_line_length {
+            Some(max) => truncate_line(&line, &spans, max),
+            None => (line, spans),
+        };
+        let line = if highlight {
+            highlight_line(config.styler, &line, &spans, &config.styles, config.highlight_color, 
+                           config.highlight_bg, 
+                           config.line_color)
+        } else {
+            line
+        };
+        let occurrences = Some(n_occurrences).filter(|_| config.count_occurrences);
+
+        // print the line with its context
+        if let Some(windows) = &mut windows {
+            let mut rendered = Vec::<u8>::new();
+            print_line(config, filename, n_line, offset, column, occurrences, &line, ':', 
+                       &mut rendered)?;
+            windows.push(rendered, true, out)?;
+            continue;
+        }
+        let first = before.front().map_or(n_line, |(n, _, _)| *n);
+        if let (true, Some(separator)) = (has_context, &separator) {
+            // only between groups which are neither contiguous nor overlapping
+            if last_printed.is_some_and(|last| first > last + 1) {
+                write!(out, "{}", separator).map_err(not_writable)?;
+            }
+        }
+        for (n, o, l) in before.drain(..) {
+            print_context(config, filename, n, o, &l, out)?;
+        }
+        print_line(config, filename, n_line, offset, column, occurrences, &line, ':', out)?;
+        last_printed = Some(n_line);
+        after_left = config.after;
+    }
+    state.lines_scanned.fetch_add(n_scanned, Ordering::Relaxed);
+    if let Some(windows) = &mut windows {
+        windows.finish(out)?;
+    }
+
+    // print only the name of the file if requested; with `files_without_match`, that is left to
+    // the caller
+    if config.files_without_match {
+        return Ok(n_selected);
+    }
+    if config.files_with_matches {
+        if n_selected > 0 && !config.quiet {
+            print_filename(config, name, out)?;
+        }
+        return Ok(n_selected);
+    }
+
+    // print only the number of lines if requested; with `rank`, that is left to the caller
+    if config.count && !config.quiet && !config.rank {
+        match filename {
+            Some(name) => write!(out, "{}{}{}{}", 
+                                 config.styler.add_fg_named(name.to_string(), Color::Magenta),
+                                 if config.null { '\0' } else { ':' }, n_selected, 
+                                 line_terminator(config)),
+            None => write!(out, "{}{}", n_selected, line_terminator(config)),
+        }.map_err(not_writable)?;
+    }
+    Ok(n_selected)
+}
+
+
+/// Error for a failed read of a line of the file `name`
+fn line_error(name: &str, e: &io::Error) -> MinigrepError {
+    if e.kind() == io::ErrorKind::InvalidData {
+        MinigrepError::InvalidUtf8(name.to_string())
+    } else if compression::is_compressed(name) {
+        MinigrepError::Decompression(name.to_string(), compression::compression(name).name())
+    } else {
+        MinigrepError::NotReadable(name.to_string())
+    }
+}
+
+
+/// Replace the given byte ranges of a line by a string
+///
+/// Empty ranges and the parts of ranges overlapping previous ones are ignored, as in 
+/// `highlight`. Return the new line with the byte ranges of the replacements in it.
+fn replace_spans(line: &str, spans: &[(usize, usize)], replacement: &str) 
+    -> (String, Vec<(usize, usize)>) {
+    let mut spans = spans.to_vec();
+    spans.sort_unstable();
+    let mut res = String::with_capacity(line.len());
+    let mut new_spans = Vec::<(usize, usize)>::with_capacity(spans.len());
+    let mut last = 0;
+    for (start, end) in spans {
+        let start = start.max(last);
+        if start >= end {
+            continue;
+        }
+        res.push_str(&line[last..start]);
+        new_spans.push((res.len(), res.len() + replacement.len()));
+        res.push_str(replacement);
+        last = end;
+    }
+    res.push_str(&line[last..]);
+    (res, new_spans)
+}
+
+
+/// Replace the tabs of a line by spaces, up to the next multiple of `width` characters
+///
+/// The byte ranges are moved accordingly; a range containing a tab contains all the spaces 
+/// replacing it. Return the new line with the new byte ranges.
+fn expand_tabs(line: &str, spans: &[(usize, usize)], width: usize) 
+    -> (String, Vec<(usize, usize)>) {
+    let mut res = String::with_capacity(line.len());
+    // byte offset in the new line of each byte offset of the line
+    let mut offsets = vec![0; line.len() + 1];
+    let mut n_chars = 0;
+    for (i, c) in line.char_indices() {
+        for offset in &mut offsets[i..i + c.len_utf8()] {
+            *offset = res.len();
+        }
+        if c == '\t' {
+            let n_spaces = width - n_chars % width;
+            res.extend(std::iter::repeat_n(' ', n_spaces));
+            n_chars += n_spaces;
+        } else {
+            res.push(c);
+            n_chars += 1;
+        }
+    }
+    offsets[line.len()] = res.len();
+    let spans = spans.iter().map(|&(start, end)| (offsets[start], offsets[end])).collect();
+    (res, spans)
+}
+
+
+/// Keep at most `max` characters of a line, replacing the others by an ellipsis
+///
+/// The characters kept are the first ones, unless the first range would then be cut off; they 
+/// are then centred on it, or start with it if it is longer than `max` characters. The byte 
+/// ranges are moved accordingly, and cut at the ends of the characters kept. Return the new line 
+/// with the new byte ranges.
+fn truncate_line(line: &str, spans: &[(usize, usize)], max: usize) 
+    -> (String, Vec<(usize, usize)>) {
+    // byte offsets of the characters, and of the end of the line
+    let offsets = line.char_indices()
+                      .map(|(i, _)| i)
+                      .chain(std::iter::once(line.len()))
+                      .collect::<Vec<usize>>();
+    let n_chars = offsets.len() - 1;
+    if n_chars <= max {
+        return (line.to_string(), spans.to_vec());
+    }
+    let char_index = |byte: usize| offsets.partition_point(|&offset| offset < byte);
+    let first = match spans.iter().min() {
+        Some(&(start, end)) if char_index(end) > max => {
+            let (start, end) = (char_index(start), char_index(end));
+            if end - start >= max { start } else { ((start + end) / 2).saturating_sub(max / 2) }
+        },
+        _ => 0,
+    }.min(n_chars - max);
+    let (start, end) = (offsets[first], offsets[first + max]);
+    let prefix = if first > 0 { "…" } else { "" };
+    let suffix = if first + max < n_chars { "…" } else { "" };
+    let spans = spans.iter()
+                     .filter(|&&(s, e)| e >= start && s <= end)
+                     .map(|&(s, e)| {
+                         let shift = |i: usize| i.clamp(start, end) - start + prefix.len();
+                         (shift(s), shift(e))
+                     })
+                     .collect();
+    (format!("{}{}{}", prefix, &line[start..end], suffix), spans)
+}
+
+
+/// Line as printed without highlighting, with its tabs expanded if `config.tab_width` is set and
+/// truncated if `config.max_line_length` is
+fn expanded(config: &Config, line: &str) -> String {
+    let line = match config.tab_width {
+        Some(width) => expand_tabs(line, &[], width).0,
+        None => line.to_string(),
+    };
+    match config.max_line_length {
+        Some(max) => truncate_line(&line, &[], max).0,
+        None => line,
+    }
+}
+
+
+/// Write the name of a file on its own line, coloured only if a highlight colour is set
+///
+/// With `config.null`, the name is followed by a NUL byte instead of `line_terminator(config)`.
+fn print_filename(config: &Config, name: &str, out: &mut dyn Write) -> Result<(), MinigrepError> {
+    let name = match config.highlight_color {
+        Some((r, g, b)) => config.styler.add_fg(name.to_string(), r, g, b),
+        None => name.to_string(),
+    };
+    write!(out, "{}{}", name, if config.null { '\0' } else { line_terminator(config) })
+        .map_err(not_writable)
+}
+
+
+/// Serialize a line to a JSON object
+fn json_line(filename: &str, n_line: usize, line: &str) -> String {
+    format!("{{\"line\": {}, \"text\": {}, \"file\": {}}}", 
+            n_line + 1, json_string(line), json_string(filename))
+}
+
+
+/// Serialize a string to JSON, with the quotes
+fn json_string(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+
+/// Write the header of the CSV output if it is requested and lines are to be printed
+///
+/// The file name is the first column if `with_names` is `true`.
+fn print_csv_header(config: &Config, with_names: bool, out: &mut dyn Write) 
+    -> Result<(), MinigrepError> {
+    if !config.csv || config.count || config.quiet || config.files_with_matches 
+        || config.files_without_match {
+        return Ok(());
+    }
+    let header = if with_names { "file,line_number,text" } else { "line_number,text" };
+    write!(out, "{}{}", header, line_terminator(config)).map_err(not_writable)
+}
+
+
+/// Serialize a line to a CSV row, with the file name first if it is not `None`
+fn csv_line(filename: Option<&str>, n_line: usize, line: &str) -> String {
+    match filename {
+        Some(name) => format!("{},{},{}", csv_field(name), n_line + 1, csv_field(line)),
+        None => format!("{},{}", n_line + 1, csv_field(line)),
+    }
+}
+
+
+/// Quote a CSV field, doubling the quotes it contains
+fn csv_field(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+
+/// Write a context line, dimmed if `config.highlight_context` is `true`
+fn print_context(config: &Config, filename: Option<&str>, n_line: usize, offset: usize, 
+                 line: &str, out: &mut dyn Write) -> Result<(), MinigrepError> {
+    let line = &expanded(config, line);
+    if config.highlight_context {
+        let line = config.styler.add_style(line.to_string(), 2);
+        print_line(config, filename, n_line, offset, None, None, &line, '-', out)
+    } else {
+        print_line(config, filename, n_line, offset, None, None, line, '-', out)
+    }
+}
+
+
+/// Write a line with its prefixes
+///
+/// `offset` is the byte offset of the line in its file; it is only printed if 
+/// `config.byte_offset` is `true`. `column` is the column of the first match, if any; it is only
+/// printed if `config.column` is `true`. `occurrences` is the number of occurrences in the line,
+/// printed after the column if it is given. `sep` separates the prefixes from each other and 
+/// from the line, except the file name which is followed by a NUL byte with `config.null`. The 
+/// line is terminated by `line_terminator(config)`.
+#[allow(clippy::too_many_arguments)]
+fn print_line(config: &Config, filename: Option<&str>, n_line: usize, offset: usize, 
+              column: Option<usize>, occurrences: Option<usize>, line: &str, sep: char, 
+              out: &mut dyn Write) -> Result<(), MinigrepError> {
+    let mut prefix = String::new();
+    if let Some(name) = filename {
+        prefix.push_str(&config.styler.add_fg_named(name.to_string(), Color::Magenta));
+        prefix.push(if config.null { '\0' } else { sep });
+    }
+    if config.line_numbers {
+        prefix.push_str(&config.styler.add_fg_named((n_line + 1).to_string(), Color::Green));
+        prefix.push(sep);
+    }
+    if config.byte_offset {
+        prefix.push_str(&config.styler.add_fg_named(offset.to_string(), Color::Green));
+        prefix.push(sep);
+    }
+    if let (true, Some(column)) = (config.column, column) {
+        prefix.push_str(&config.styler.add_fg_named(column.to_string(), Color::Green));
+        prefix.push(sep);
+    }
+    if let Some(occurrences) = occurrences {
+        prefix.push_str(&config.styler.add_fg_named(occurrences.to_string(), Color::Green));
+        prefix.push(sep);
+    }
+    write!(out, "{}{}{}", prefix, line, line_terminator(config)).map_err(not_writable)
+}
+
+
+/// Character terminating the printed lines: a NUL byte with `config.line_null`, a newline 
+/// otherwise
+fn line_terminator(config: &Config) -> char {
+    if config.line_null { '\0' } else { '\n' }
+}
+
+
+/// Iterate over the lines of a reader, with the byte offset at which each of them starts
+///
+/// As for `BufRead::lines`, the lines do not include the final `\n` or `\r\n`; the offsets 
+/// account for them. Lines which are not valid UTF-8 are an error of kind `InvalidData`, unless
+/// `lossy` is `true`; the invalid sequences are then replaced by `U+FFFD`.
+fn lines_with_offsets(mut reader: impl BufRead, lossy: bool) 
+    -> impl Iterator<Item = io::Result<(usize, String)>> {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let mut bytes = Vec::<u8>::new();
+        match reader.read_until(b'\n', &mut bytes) {
+            Ok(0) => None,
+            Ok(n) => {
+                let start = offset;
+                offset += n;
+                let mut line = match String::from_utf8(bytes) {
+                    Ok(line) => line,
+                    Err(e) if lossy => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                    Err(_) => {
+                        return Some(Err(io::Error::new(io::ErrorKind::InvalidData, 
+                                                       "stream did not contain valid UTF-8")));
+                    },
+                };
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Some(Ok((start, line)))
+            },
+            Err(e) => Some(Err(e)),
+        }
+    })
+}
+
+
+/// Column (starting from 1) of the character starting at a given byte in a line
+///
+/// Columns are counted in Unicode scalar values.
+fn char_column(line: &str, byte: usize) -> usize {
+    line[..byte].chars().count() + 1
+}
+
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Config {
+    queries: Vec<String>,
+    filenames: Vec<String>,
+    styles: Vec<u8>,
+    case: CaseMode,
+    suppress_filename: bool,
+    with_filename: bool,
+    line_numbers: bool,
+    invert: bool,
+    count: bool,
+    recursive: bool,
+    files_from: Option<String>,
+    before: usize,
+    after: usize,
+    highlight_context: bool,
+    regex: bool,
+    fixed: bool,
+    glob: bool,
+    word: bool,
+    trim: bool,
+    normalize_whitespace: bool,
+    highlight_color: Option<(u8, u8, u8)>,
+    json: bool,
+    max_count: Option<usize>,
+    only_matching: bool,
+    byte_offset: bool,
+    column: bool,
+    total: bool,
+    quiet: bool,
+    timeout: Option<u64>,
+    files_with_matches: bool,
+    files_without_match: bool,
+    null: bool,
+    color: ColorChoice,
+    threads: Option<usize>,
+    binary_mode: BinaryMode,
+    line_match: bool,
+    csv: bool,
+    max_depth: Option<usize>,
+    follow: bool,
+    respect_gitignore: bool,
+    pattern_files: Vec<String>,
+    color_mode: ColorMode,
+    replace: Option<String>,
+    progress: bool,
+    summary: bool,
+    group_separator: Option<String>,
+    context_separator: Option<String>,
+    passthrough: bool,
+    line_color: Option<(u8, u8, u8)>,
+    count_occurrences: bool,
+    byte_start: usize,
+    byte_end: Option<usize>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    name: bool,
+    explain: bool,
+    tab_width: Option<usize>,
+    highlight_bg: Option<(u8, u8, u8)>,
+    field: Option<usize>,
+    field_separator: String,
+    rank: bool,
+    rank_all: bool,
+    no_merge_context: bool,
+    line_null: bool,
+    max_line_length: Option<usize>,
+    styler: Styler,
+}
+
+
+impl Config {
+    /// Create a new Config from an array of arguments
+    /// 
+    /// # Argument
+    ///
+    /// `args`: iterator of `String` with at least 3 elements, such as `env::args()`; flags may 
+    /// appear anywhere after the first one, and the values of long flags may be given after `=` 
+    /// (`--color=ff8800`). All the arguments after `--` are positional.
+    ///
+    /// # Errors
+    ///
+    /// * `MissingQuery` if there is no argument and no `-e` or `-f` flag
+    /// * `MissingFilename` if there is only one argument, or none with `-e`, and no flag 
+    ///   `--files-from`
+    /// * `MissingValue` if a flag expecting a value is the last argument
+    /// * `InvalidValue` if the value of a flag can not be parsed, if the value of `--style` is not
+    ///   a comma-separated list of supported styles (see `style::STYLES`), if the value of 
+    ///   `--threads`, `--expand-tabs`, `--field`, or `--max-line-length` is 0, if that of 
+    ///   `--field-sep` is empty, if that of `--binary-files` or `--color-mode` is not a known 
+    ///   mode, or if that of `--byte-end` is smaller than that of `--byte-start`
+    /// * `InvalidColor` if the value of `--color` is neither `auto`, `always`, `never`, nor a 
+    ///   valid hexadecimal colour, or if that of `--color-match`, `--color-line`, or 
+    ///   `--highlight-bg` is not a valid hexadecimal colour
+    /// * `UnknownFlag` if an argument starting with `-` is not a known flag
+    /// * `UnexpectedValue` if a value is given with `=` to a flag which does not expect one
+    /// * `ConflictingFlags` if both `-l` and `-L` are present, or both `--json` and `--csv`
+    /// * `InvalidRegex` if the value of `--include` or `--exclude` is not a valid glob pattern
+    ///
+    /// # Warnings
+    ///
+    /// * `InvalidStyle` if the style is made of digits but is not a `u8`
+    /// * `UnsupportedStyle` if the style is a `u8` but not one of `style::STYLES`; it is then 
+    ///   ignored
+    ///
+    /// # Values
+    ///
+    /// * `queries` are given by the values of the flags `-e` and the terms of the values of the 
+    ///   flags `--any` or, if there is none and no flag `-f`, by the first argument.
+    /// * `pattern_files` are given by the values of the flags `-f`; their lines are added to the 
+    ///   queries by `run`.
+    /// * `filenames` are given by the following arguments (all of them with `-e` or `-f`), 
+    ///   except a last one made only of digits if there are at least three arguments; that one is
+    ///   converted to a `u8` and set as the only element of `styles`.
+    /// * `styles` are otherwise given by the value of the flag `--style`, a comma-separated list
+    ///   of integers, and are empty if there is no such flag.
+    /// * With the flag `--reverse-match`, `styles` are only 7 (reverse video) and there is neither
+    ///   `highlight_color` nor `highlight_bg`, whatever the other flags.
+    /// * `case` is set to `CaseMode::Insensitive` if the flag `-i` is present, to 
+    ///   `CaseMode::Sensitive` if the flag `-s` is present, and to `CaseMode::Smart` if the flag 
+    ///   `--smart-case` is present; if several are, the last one wins. If none is, `case` is set 
+    ///   to `CaseMode::Sensitive` if the environment variable `CASE_INSENSITIVE` is not set and 
+    ///   to `CaseMode::Insensitive` if it is set.
+    /// * `suppress_filename` is set to `true` if the flag `-h` is present, and `with_filename` if 
+    ///   the flag `-H` is present; if both are, only the last one is set.
+    /// * `line_numbers` is set to `true` if the flag `-n` is present.
+    /// * `invert` is set to `true` if the flag `-v` is present.
+    /// * `count` is set to `true` if the flag `-c` is present, and both `count` and `invert` if
+    ///   the flag `--count-nonmatch` is present.
+    /// * `rank` is set to `true` if the flag `--rank` or `--rank-all` is present, and `rank_all`
+    ///   if the flag `--rank-all` is; both also set `count`.
+    /// * `recursive` is set to `true` if the flag `-r` is present.
+    /// * `max_depth` is given by the value of the flag `--max-depth`, if present.
+    /// * `follow` is set to `true` if the flag `--follow` is present.
+    /// * `respect_gitignore` is set to `true` if the flag `--respect-gitignore` is present.
+    /// * `include` and `exclude` are given by the values of the flags `--include` and 
+    ///   `--exclude`, respectively, in order.
+    /// * `name` is set to `true` if the flag `--name` is present.
+    /// * `explain` is set to `true` if the flag `--explain` or `--dry-run` is present.
+    /// * `files_from` is given by the value of the flag `--files-from`, if present.
+    /// * `before` and `after` are given by the values of the flags `-B` and `-A`, respectively, 
+    ///   or both by the value of the flag `-C`. They take the value 0 if not set.
+    /// * `highlight_context` is set to `true` if the flag `--highlight-context` is present.
+    /// * `regex` is set to `true` if the flag `--regex` is present.
+    /// * `fixed` is set to `true` if the flag `-F` is present; the query is then matched 
+    ///   literally, even if `regex` or `glob` is `true`.
+    /// * `glob` is set to `true` if the flag `--glob` is present; the query is then a glob 
+    ///   pattern, even if `regex` is `true`.
+    /// * `word` is set to `true` if the flag `-w` is present.
+    /// * `line_match` is set to `true` if the flag `-x` is present.
+    /// * `trim` is set to `true` if the flag `--trim` is present.
+    /// * `normalize_whitespace` is set to `true` if the flag `--normalize-whitespace` is present.
+    /// * `highlight_color` is given by the value of the flag `--color` or `--color-match`, in 
+    ///   hexadecimal format, `highlight_bg` by that of the flag `--highlight-bg`, and `line_color`
+    ///   by that of the flag `--color-line`.
+    /// * `json` is set to `true` if the flag `--json` is present.
+    /// * `replace` is given by the value of the flag `--replace`, if present.
+    /// * `progress` is set to `true` if the flag `--progress` is present.
+    /// * `summary` is set to `true` if the flag `--summary` is present.
+    /// * `count_occurrences` is set to `true` if the flag `--count-per-line` is present.
+    /// * `byte_start` and `byte_end` are given by the values of the flags `--byte-start` and 
+    ///   `--byte-end`; `byte_start` is 0 if it is absent, and `byte_end` is `None`.
+    /// * `passthrough` is set to `true` if the flag `--passthrough` is present.
+    /// * `no_merge_context` is set to `true` if the flag `--no-merge-context` or `--window` is 
+    ///   present.
+    /// * `group_separator` is given by the value of the flag `--group-separator`, and is an 
+    ///   empty string if it is absent; it is `None` if the flag `--no-group-separator` is 
+    ///   present after all those `--group-separator`.
+    /// * `context_separator` is given by the value of the flag `--context-separator`, and is 
+    ///   `--` if it is absent; it is `None` if the flag `--no-separator` is present after all 
+    ///   those `--context-separator`.
+    /// * `csv` is set to `true` if the flag `--csv` is present.
+    /// * `max_count` is given by the value of the flag `-m`, if present.
+    /// * `only_matching` is set to `true` if the flag `-o` is present.
+    /// * `byte_offset` is set to `true` if the flag `-b` is present.
+    /// * `column` is set to `true` if the flag `--column` is present.
+    /// * `total` and `count` are set to `true` if the flag `--total` is present.
+    /// * `quiet` is set to `true` if the flag `-q` is present.
+    /// * `timeout` is given by the value of the flag `--timeout`, in seconds, if present.
+    /// * `files_with_matches` is set to `true` if the flag `-l` is present.
+    /// * `files_without_match` is set to `true` if the flag `-L` is present.
+    /// * `null` is set to `true` if the flag `-Z` is present.
+    /// * `line_null` is set to `true` if the flag `--line-null` is present.
+    /// * `color` is given by the value of the flag `--color` if it is `auto`, `always`, or 
+    ///   `never`, and is `ColorChoice::Auto` otherwise.
+    /// * `color_mode` is given by the value of the flag `--color-mode` (`truecolor`, `256`, or 
+    ///   `ansi`), and is `ColorMode::TrueColor` if it is absent.
+    /// * `styler` writes the colours and styles according to `color` and `color_mode`, for an 
+    ///   output which is not a terminal; `run` sets it again if the standard output is one.
+    /// * `threads` is given by the value of the flag `--threads`, if present; it must not be 0.
+    /// * `field` and `field_separator` are given by the values of the flags `--field` and 
+    ///   `--field-sep`; `field_separator` is a tab if the flag is absent.
+    /// * `tab_width` is given by the value of the flag `--expand-tabs=N`, which must not be 0, or 
+    ///   is 8 if the flag is given without a value.
+    /// * `max_line_length` is given by the value of the flag `--max-line-length`, if present; it
+    ///   must not be 0.
+    /// * `binary_mode` is given by the value of the flag `--binary-files` (`binary`, `text`, or 
+    ///   `without-match`), set to `BinaryMode::Text` by the flag `-a`, and set to 
+    ///   `BinaryMode::WithoutMatch` by the flag `-I`; if several are present, the last one wins. 
+    ///   It is `BinaryMode::Binary` otherwise.
+    pub fn new(mut args: impl Iterator<Item = String>) -> Result<Config, MinigrepError> {
+    
+        args.next();
+        let mut builder = Config::builder();
+        parse_args(&mut builder, args)?;
+        builder.build()
+    }
+
+    /// Create a new Config from the default options of a `.minigreprc` file and an array of 
+    /// arguments
+    ///
+    /// The file is looked for in the current directory, then in the home directory; see the 
+    /// README for its format. Its entries are the long flags of `Config::new`, which are read as 
+    /// if they were given before the arguments, so that the flags of the latter take precedence.
+    /// Without such a file, the result is the same as with `Config::new`.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidConfigFile` if the file can not be read or parsed, or if one of its entries is 
+    ///   not a valid flag
+    /// * `InvalidValue` if an entry of the file sets the patterns (`pattern`, `any`, or `file`), 
+    ///   with the path of the file and the flag
+    /// * those of `Config::new`
+    pub fn from_env_and_args(mut args: impl Iterator<Item = String>) 
+        -> Result<Config, MinigrepError> {
+        args.next();
+        let mut builder = Config::builder();
+        if let Some(path) = config_file::find() {
+            let invalid = |reason: String| {
+                MinigrepError::InvalidConfigFile(path.display().to_string(), reason)
+            };
+            let flags = config_file::load(&path).map_err(invalid)?;
+            // the query on the command line would otherwise be read as a file name
+            if let Some(flag) = flags.iter().find(|flag| config_file::sets_patterns(flag)) {
+                return Err(MinigrepError::InvalidValue(path.display().to_string(), flag.clone()));
+            }
+            parse_args(&mut builder, flags.into_iter()).map_err(|e| invalid(e.to_string()))?;
+        }
+        parse_args(&mut builder, args)?;
+        builder.build()
+    }
+
+    /// Create a builder of Config, to construct one without parsing arguments
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use minigrep::Config;
+    ///
+    /// let config = Config::builder().query("you").filename("poem.txt").style(1).build();
+    /// assert!(config.is_ok());
+    /// ```
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+
+/// How the case of the letters is handled in the search
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum CaseMode {
+    /// the case must match
+    #[default]
+    Sensitive,
+    /// the case is ignored
+    Insensitive,
+    /// the case is ignored unless the query contains an uppercase letter
+    Smart,
+}
+
+impl CaseMode {
+    /// Whether the search for a query is case-sensitive
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minigrep::CaseMode;
+    ///
+    /// assert!(!CaseMode::Smart.is_sensitive("duct"));
+    /// assert!(CaseMode::Smart.is_sensitive("Duct"));
+    /// ```
+    pub fn is_sensitive(self, query: &str) -> bool {
+        match self {
+            CaseMode::Sensitive => true,
+            CaseMode::Insensitive => false,
+            CaseMode::Smart => query.chars().any(char::is_uppercase),
+        }
+    }
+}
+
+/// How the binary files are searched
+///
+/// A file is considered as binary if it contains a NUL byte in its first `BINARY_CHUNK` bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BinaryMode {
+    /// search the file, but only print whether it contains a selected line
+    #[default]
+    Binary,
+    /// search the file as if it were text
+    Text,
+    /// consider that the file contains no selected line
+    WithoutMatch,
+}
+
+impl From<bool> for CaseMode {
+    /// `Sensitive` for `true` and `Insensitive` for `false`
+    fn from(case_sensitive: bool) -> CaseMode {
+        if case_sensitive {
+            CaseMode::Sensitive
+        } else {
+            CaseMode::Insensitive
+        }
+    }
+}
+
+
+/// Builder of Config
+///
+/// Each method sets the field of the same name of Config (see `Config::new` for their meaning)
+/// and returns the builder, so that calls can be chained. The fields which are not set take the
+/// same values as when the corresponding flag is absent.
+#[derive(Debug, Default, Clone)]
+pub struct ConfigBuilder {
+    // the fields of Config which are set as they are
+    config: Config,
+    // the fields from which the others are derived by `build`
+    case: Option<CaseMode>,
+    group_separator: Option<String>,
+    no_group_separator: bool,
+    context_separator: Option<String>,
+    no_separator: bool,
+    reverse_match: bool,
+    field_separator: Option<String>,
+}
+
+impl ConfigBuilder {
+    /// add a string to search for; the lines containing any of them are selected
+    pub fn query(&mut self, query: &str) -> &mut ConfigBuilder {
+        self.config.queries.push(query.to_string());
+        self
+    }
+
+    /// add a file to read patterns from, one per line, or `-` for the standard input (`-f`)
+    pub fn pattern_file(&mut self, pattern_file: &str) -> &mut ConfigBuilder {
+        self.config.pattern_files.push(pattern_file.to_string());
+        self
+    }
+
+    /// add a glob pattern of the names of the files to search in the directories (`--include`)
+    pub fn include(&mut self, pattern: &str) -> &mut ConfigBuilder {
+        self.config.include.push(pattern.to_string());
+        self
+    }
+
+    /// add a glob pattern of the names of the files to skip in the directories (`--exclude`)
+    pub fn exclude(&mut self, pattern: &str) -> &mut ConfigBuilder {
+        self.config.exclude.push(pattern.to_string());
+        self
+    }
+
+    /// add a file to search in (`-` for the standard input)
+    pub fn filename(&mut self, filename: &str) -> &mut ConfigBuilder {
+        self.config.filenames.push(filename.to_string());
+        self
+    }
+
+    /// style of the matches
+    pub fn style(&mut self, style: u8) -> &mut ConfigBuilder {
+        self.config.styles = vec![style];
+        self
+    }
+
+    /// styles of the matches, all applied together
+    pub fn styles(&mut self, styles: &[u8]) -> &mut ConfigBuilder {
+        self.config.styles = styles.to_vec();
+        self
+    }
+
+    /// case sensitivity; if not set, it is given by the environment variable `CASE_INSENSITIVE`
+    pub fn case_sensitive(&mut self, case_sensitive: bool) -> &mut ConfigBuilder {
+        self.case(CaseMode::from(case_sensitive))
+    }
+
+    /// case handling, with the same default as `case_sensitive`
+    pub fn case(&mut self, case: CaseMode) -> &mut ConfigBuilder {
+        self.case = Some(case);
+        self
+    }
+
+    /// never print the file names before the lines (`-h`); unsets `with_filename` if `true`
+    pub fn suppress_filename(&mut self, suppress_filename: bool) -> &mut ConfigBuilder {
+        self.config.suppress_filename = suppress_filename;
+        self.config.with_filename &= !suppress_filename;
+        self
+    }
+
+    /// always print the file names before the lines (`-H`); unsets `suppress_filename` if `true`
+    pub fn with_filename(&mut self, with_filename: bool) -> &mut ConfigBuilder {
+        self.config.with_filename = with_filename;
+        self.config.suppress_filename &= !with_filename;
+        self
+    }
+
+    /// print the line numbers (`-n`)
+    pub fn line_numbers(&mut self, line_numbers: bool) -> &mut ConfigBuilder {
+        self.config.line_numbers = line_numbers;
+        self
+    }
+
+    /// select the lines which do not match (`-v`)
+    pub fn invert(&mut self, invert: bool) -> &mut ConfigBuilder {
+        self.config.invert = invert;
+        self
+    }
+
+    /// only print the number of selected lines (`-c`)
+    pub fn count(&mut self, count: bool) -> &mut ConfigBuilder {
+        self.config.count = count;
+        self
+    }
+
+    /// only print the names of the files with their numbers of selected lines, sorted by 
+    /// decreasing number (`--rank`)
+    pub fn rank(&mut self, rank: bool) -> &mut ConfigBuilder {
+        self.config.rank = rank;
+        self
+    }
+
+    /// as `rank`, including the files without any selected line (`--rank-all`)
+    pub fn rank_all(&mut self, rank_all: bool) -> &mut ConfigBuilder {
+        self.config.rank_all = rank_all;
+        self
+    }
+
+    /// search the directories recursively (`-r`)
+    pub fn recursive(&mut self, recursive: bool) -> &mut ConfigBuilder {
+        self.config.recursive = recursive;
+        self
+    }
+
+    /// only descend this number of levels into the directories (`--max-depth`)
+    pub fn max_depth(&mut self, max_depth: usize) -> &mut ConfigBuilder {
+        self.config.max_depth = Some(max_depth);
+        self
+    }
+
+    /// follow the symbolic links to directories (`--follow`)
+    pub fn follow(&mut self, follow: bool) -> &mut ConfigBuilder {
+        self.config.follow = follow;
+        self
+    }
+
+    /// skip the files ignored by the `.gitignore` files in the recursive search 
+    /// (`--respect-gitignore`)
+    pub fn respect_gitignore(&mut self, respect_gitignore: bool) -> &mut ConfigBuilder {
+        self.config.respect_gitignore = respect_gitignore;
+        self
+    }
+
+    /// also search the files listed in this file, or in the standard input if it is `-` 
+    /// (`--files-from`)
+    pub fn files_from(&mut self, list: &str) -> &mut ConfigBuilder {
+        self.config.files_from = Some(list.to_string());
+        self
+    }
+
+    /// number of lines of context before each selected line (`-B`)
+    pub fn before(&mut self, before: usize) -> &mut ConfigBuilder {
+        self.config.before = before;
+        self
+    }
+
+    /// number of lines of context after each selected line (`-A`)
+    pub fn after(&mut self, after: usize) -> &mut ConfigBuilder {
+        self.config.after = after;
+        self
+    }
+
+    /// dim the context lines (`--highlight-context`)
+    pub fn highlight_context(&mut self, highlight_context: bool) -> &mut ConfigBuilder {
+        self.config.highlight_context = highlight_context;
+        self
+    }
+
+    /// interpret the query as a regular expression (`-E`)
+    pub fn regex(&mut self, regex: bool) -> &mut ConfigBuilder {
+        self.config.regex = regex;
+        self
+    }
+
+    /// match the query literally, even if `regex` or `glob` is set (`-F`)
+    pub fn fixed(&mut self, fixed: bool) -> &mut ConfigBuilder {
+        self.config.fixed = fixed;
+        self
+    }
+
+    /// interpret the query as a glob pattern, even if `regex` is set (`--glob`)
+    pub fn glob(&mut self, glob: bool) -> &mut ConfigBuilder {
+        self.config.glob = glob;
+        self
+    }
+
+    /// only match whole words (`-w`)
+    pub fn word(&mut self, word: bool) -> &mut ConfigBuilder {
+        self.config.word = word;
+        self
+    }
+
+    /// remove the whitespace at the start and end of the queries (`--trim`)
+    pub fn trim(&mut self, trim: bool) -> &mut ConfigBuilder {
+        self.config.trim = trim;
+        self
+    }
+
+    /// consider runs of whitespace as a single space (`--normalize-whitespace`)
+    pub fn normalize_whitespace(&mut self, normalize_whitespace: bool) -> &mut ConfigBuilder {
+        self.config.normalize_whitespace = normalize_whitespace;
+        self
+    }
+
+    /// colour of the matches (`--color RGB` or `--color-match RGB`)
+    pub fn highlight_color(&mut self, color: (u8, u8, u8)) -> &mut ConfigBuilder {
+        self.config.highlight_color = Some(color);
+        self
+    }
+
+    /// only search the field number `n`, starting from 1, of the lines (`--field`)
+    pub fn field(&mut self, n: usize) -> &mut ConfigBuilder {
+        self.config.field = Some(n);
+        self
+    }
+
+    /// separator of the fields of the lines, a tab by default (`--field-sep`)
+    pub fn field_separator(&mut self, separator: &str) -> &mut ConfigBuilder {
+        self.field_separator = Some(separator.to_string());
+        self
+    }
+
+    /// background colour of the matches (`--highlight-bg RGB`)
+    pub fn highlight_bg(&mut self, color: (u8, u8, u8)) -> &mut ConfigBuilder {
+        self.config.highlight_bg = Some(color);
+        self
+    }
+
+    /// colour of the rest of the selected lines (`--color-line RGB`)
+    pub fn line_color(&mut self, color: (u8, u8, u8)) -> &mut ConfigBuilder {
+        self.config.line_color = Some(color);
+        self
+    }
+
+    /// print the selected lines as JSON (`--json`)
+    pub fn json(&mut self, json: bool) -> &mut ConfigBuilder {
+        self.config.json = json;
+        self
+    }
+
+    /// print the selected lines as CSV rows (`--csv`)
+    pub fn csv(&mut self, csv: bool) -> &mut ConfigBuilder {
+        self.config.csv = csv;
+        self
+    }
+
+    /// stop reading each file after this number of selected lines (`-m`)
+    pub fn max_count(&mut self, max_count: usize) -> &mut ConfigBuilder {
+        self.config.max_count = Some(max_count);
+        self
+    }
+
+    /// only print the matches (`-o`)
+    pub fn only_matching(&mut self, only_matching: bool) -> &mut ConfigBuilder {
+        self.config.only_matching = only_matching;
+        self
+    }
+
+    /// print the lines with the matches replaced by a string (`--replace`)
+    pub fn replace(&mut self, replacement: &str) -> &mut ConfigBuilder {
+        self.config.replace = Some(replacement.to_string());
+        self
+    }
+
+    /// print the byte offset of the lines (`-b`)
+    pub fn byte_offset(&mut self, byte_offset: bool) -> &mut ConfigBuilder {
+        self.config.byte_offset = byte_offset;
+        self
+    }
+
+    /// print the column of the first match (`--column`)
+    pub fn column(&mut self, column: bool) -> &mut ConfigBuilder {
+        self.config.column = column;
+        self
+    }
+
+    /// write the progress of the search of each file to the standard error (`--progress`)
+    pub fn progress(&mut self, progress: bool) -> &mut ConfigBuilder {
+        self.config.progress = progress;
+        self
+    }
+
+    /// line written between the outputs of successive files (`--group-separator`)
+    pub fn group_separator(&mut self, separator: &str) -> &mut ConfigBuilder {
+        self.group_separator = Some(separator.to_string());
+        self.no_group_separator = false;
+        self
+    }
+
+    /// write nothing between the outputs of successive files (`--no-group-separator`)
+    pub fn no_group_separator(&mut self, no_group_separator: bool) -> &mut ConfigBuilder {
+        self.no_group_separator = no_group_separator;
+        self
+    }
+
+    /// line written between the groups of context lines (`--context-separator`)
+    pub fn context_separator(&mut self, separator: &str) -> &mut ConfigBuilder {
+        self.context_separator = Some(separator.to_string());
+        self.no_separator = false;
+        self
+    }
+
+    /// write nothing between the groups of context lines (`--no-separator`)
+    pub fn no_separator(&mut self, no_separator: bool) -> &mut ConfigBuilder {
+        self.no_separator = no_separator;
+        self
+    }
+
+    /// highlight the matches with reverse video (style 7) instead of the styles and colours set 
+    /// otherwise (`--reverse-match`)
+    pub fn reverse_match(&mut self, reverse_match: bool) -> &mut ConfigBuilder {
+        self.reverse_match = reverse_match;
+        self
+    }
+
+    /// describe the search on the standard error instead of doing it (`--explain`)
+    pub fn explain(&mut self, explain: bool) -> &mut ConfigBuilder {
+        self.config.explain = explain;
+        self
+    }
+
+    /// search the names of the files instead of their content (`--name`)
+    pub fn name(&mut self, name: bool) -> &mut ConfigBuilder {
+        self.config.name = name;
+        self
+    }
+
+    /// print each selected line in its own block of context, even if the blocks overlap 
+    /// (`--no-merge-context`)
+    pub fn no_merge_context(&mut self, no_merge_context: bool) -> &mut ConfigBuilder {
+        self.config.no_merge_context = no_merge_context;
+        self
+    }
+
+    /// print all the lines, with the matches highlighted in the selected ones (`--passthrough`)
+    pub fn passthrough(&mut self, passthrough: bool) -> &mut ConfigBuilder {
+        self.config.passthrough = passthrough;
+        self
+    }
+
+    /// print the number of occurrences in each selected line (`--count-per-line`)
+    pub fn count_occurrences(&mut self, count_occurrences: bool) -> &mut ConfigBuilder {
+        self.config.count_occurrences = count_occurrences;
+        self
+    }
+
+    /// only search the lines starting at this byte offset or after it (`--byte-start`)
+    pub fn byte_start(&mut self, byte_start: usize) -> &mut ConfigBuilder {
+        self.config.byte_start = byte_start;
+        self
+    }
+
+    /// only search the lines starting before this byte offset (`--byte-end`)
+    pub fn byte_end(&mut self, byte_end: usize) -> &mut ConfigBuilder {
+        self.config.byte_end = Some(byte_end);
+        self
+    }
+
+    /// write a summary of the search to the standard error (`--summary`)
+    pub fn summary(&mut self, summary: bool) -> &mut ConfigBuilder {
+        self.config.summary = summary;
+        self
+    }
+
+    /// print the total number of selected lines (`--total`); also sets `count` if `true`
+    pub fn total(&mut self, total: bool) -> &mut ConfigBuilder {
+        self.config.total = total;
+        self.config.count |= total;
+        self
+    }
+
+    /// print nothing and stop at the first selected line (`-q`)
+    pub fn quiet(&mut self, quiet: bool) -> &mut ConfigBuilder {
+        self.config.quiet = quiet;
+        self
+    }
+
+    /// stop the search after this number of seconds (`--timeout`)
+    pub fn timeout(&mut self, seconds: u64) -> &mut ConfigBuilder {
+        self.config.timeout = Some(seconds);
+        self
+    }
+
+    /// only print the names of the files with a selected line (`-l`)
+    pub fn files_with_matches(&mut self, files_with_matches: bool) -> &mut ConfigBuilder {
+        self.config.files_with_matches = files_with_matches;
+        self
+    }
+
+    /// only print the names of the files without a selected line (`-L`)
+    pub fn files_without_match(&mut self, files_without_match: bool) -> &mut ConfigBuilder {
+        self.config.files_without_match = files_without_match;
+        self
+    }
+
+    /// end the file names with a NUL byte (`-Z`)
+    pub fn null(&mut self, null: bool) -> &mut ConfigBuilder {
+        self.config.null = null;
+        self
+    }
+
+    /// end the printed lines with a NUL byte instead of a newline (`--line-null`)
+    pub fn line_null(&mut self, line_null: bool) -> &mut ConfigBuilder {
+        self.config.line_null = line_null;
+        self
+    }
+
+    /// when to use colours and styles (`--color WHEN`)
+    pub fn color(&mut self, color: ColorChoice) -> &mut ConfigBuilder {
+        self.config.color = color;
+        self
+    }
+
+    /// how colours are written (`--color-mode`)
+    pub fn color_mode(&mut self, color_mode: ColorMode) -> &mut ConfigBuilder {
+        self.config.color_mode = color_mode;
+        self
+    }
+
+    /// only select the lines equal to a query (`-x`)
+    pub fn line_match(&mut self, line_match: bool) -> &mut ConfigBuilder {
+        self.config.line_match = line_match;
+        self
+    }
+
+    /// how the binary files are searched (`--binary-files`)
+    pub fn binary_mode(&mut self, binary_mode: BinaryMode) -> &mut ConfigBuilder {
+        self.config.binary_mode = binary_mode;
+        self
+    }
+
+    /// search several files with this number of threads (`--threads`); 0 is taken as 1
+    pub fn threads(&mut self, threads: usize) -> &mut ConfigBuilder {
+        self.config.threads = Some(threads.max(1));
+        self
+    }
+
+    /// replace the tabs by spaces up to the next multiple of `width` columns in the printed 
+    /// lines (`--expand-tabs`); a width of 0 is taken as 1
+    pub fn expand_tabs(&mut self, width: usize) -> &mut ConfigBuilder {
+        self.config.tab_width = Some(width.max(1));
+        self
+    }
+
+    /// print at most this number of characters of each line, followed by an ellipsis if it is
+    /// longer (`--max-line-length`); 0 is taken as 1
+    pub fn max_line_length(&mut self, max_line_length: usize) -> &mut ConfigBuilder {
+        self.config.max_line_length = Some(max_line_length.max(1));
+        self
+    }
+
+    /// Build the Config
+    ///
+    /// # Errors
+    ///
+    /// * `MissingQuery` if no query is given, neither directly nor with `pattern_file`
+    /// * `MissingFilename` if no file is given, neither directly nor with `files_from`
+    /// * `ConflictingFlags` if both `files_with_matches` and `files_without_match` are set, or 
+    ///   both `json` and `csv`
+    /// * `InvalidValue` if `byte_end` is smaller than `byte_start`
+    /// * `InvalidRegex` if one of the patterns of `include` or `exclude` can not be compiled
+    pub fn build(&self) -> Result<Config, MinigrepError> {
+        if self.config.queries.is_empty() && self.config.pattern_files.is_empty() {
+            return Err(MinigrepError::MissingQuery);
+        }
+        if self.config.filenames.is_empty() && self.config.files_from.is_none() {
+            return Err(MinigrepError::MissingFilename);
+        }
+        if self.config.files_with_matches && self.config.files_without_match {
+            return Err(MinigrepError::ConflictingFlags("-l".to_string(), "-L".to_string()));
+        }
+        if self.config.json && self.config.csv {
+            return Err(MinigrepError::ConflictingFlags("--json".to_string(), "--csv".to_string()));
+        }
+        if let Some(end) = self.config.byte_end.filter(|&end| end < self.config.byte_start) {
+            return Err(MinigrepError::InvalidValue("--byte-end".to_string(), end.to_string()));
+        }
+        for pattern in self.config.include.iter().chain(&self.config.exclude) {
+            PathGlob::new(pattern)?;
+        }
+
+        let mut config = self.config.clone();
+
+        // the case handling set explicitly takes precedence over the environment
+        config.case = match self.case {
+            Some(case) => case,
+            None => CaseMode::from(env::var("CASE_INSENSITIVE").is_err()),
+        };
+
+        // the files are separated by an empty line unless the separator is suppressed
+        config.group_separator = match (self.no_group_separator, &self.group_separator) {
+            (true, _) => None,
+            (false, separator) => Some(separator.clone().unwrap_or_default()),
+        };
+
+        // reverse video takes precedence over the other ways to highlight the matches
+        if self.reverse_match {
+            config.styles = vec![7];
+            config.highlight_color = None;
+            config.highlight_bg = None;
+        }
+
+        // the groups of context lines are separated by `--` unless the separator is suppressed
+        config.context_separator = match (self.no_separator, &self.context_separator) {
+            (true, _) => None,
+            (false, separator) => Some(separator.clone().unwrap_or_else(|| "--".to_string())),
+        };
+
+        // the output is not known to be a terminal until `run` writes to the standard output
+        config.styler = Styler::new(self.config.color, self.config.color_mode, false);
+
+        config.field_separator = self.field_separator.clone().unwrap_or_else(|| "\t".to_string());
+
+        // ranking the files relies on the number of selected lines in each of them
+        config.rank = self.config.rank || self.config.rank_all;
+        config.count = self.config.count || config.rank;
+
+        Ok(config)
+    }
+}
+
+
+/// Parse arguments (without the name of the program) into a builder, as described in `Config::new`
+fn parse_args(builder: &mut ConfigBuilder, mut args: impl Iterator<Item = String>) 
+    -> Result<(), MinigrepError> {
+    // separate the flags from the positional arguments
+    let mut queries = Vec::<String>::new();
+    let mut positional = Vec::<String>::new();
+    let mut only_positional = false;
+    while let Some(arg) = args.next() {
+
+        // positional arguments, including `-` (standard input) and everything after `--`
+        if only_positional || arg == "-" || !arg.starts_with('-') {
+            positional.push(arg);
+            continue;
+        }
+        if arg == "--" {
+            only_positional = true;
+            continue;
+        }
+
+        // long flags may be given a value with `=`
+        let (flag, mut value) = match arg.find('=') {
+            Some(i) if arg.starts_with("--") => {
+                (arg[..i].to_string(), Some(arg[i+1..].to_string()))
+            },
+            _ => (arg, None),
+        };
+        let arg = flag.as_str();
+        match arg {
+            "-n" | "--line-number" => { builder.line_numbers(true); },
+            "-h" | "--no-filename" => { builder.suppress_filename(true); },
+            "-H" | "--with-filename" => { builder.with_filename(true); },
+            "-v" | "--invert-match" => { builder.invert(true); },
+            "-c" | "--count" => { builder.count(true); },
+            "--rank" => { builder.rank(true); },
+            "--rank-all" => { builder.rank_all(true); },
+            "--count-nonmatch" => { builder.count(true).invert(true); },
+            "--count-per-line" => { builder.count_occurrences(true); },
+            "-r" | "--recursive" => { builder.recursive(true); },
+            "--follow" => { builder.follow(true); },
+            "--respect-gitignore" => { builder.respect_gitignore(true); },
+            "--highlight-context" => { builder.highlight_context(true); },
+            "-E" | "--regex" => { builder.regex(true); },
+            "-F" | "--fixed-strings" => { builder.fixed(true); },
+            "--glob" => { builder.glob(true); },
+            "-w" | "--word-regexp" => { builder.word(true); },
+            "-x" | "--line-regexp" => { builder.line_match(true); },
+            "--trim" => { builder.trim(true); },
+            "--normalize-whitespace" => { builder.normalize_whitespace(true); },
+            "-i" | "--ignore-case" => { builder.case_sensitive(false); },
+            "-s" | "--case-sensitive" => { builder.case_sensitive(true); },
+            "--smart-case" => { builder.case(CaseMode::Smart); },
+            "--json" => { builder.json(true); },
+            "--csv" => { builder.csv(true); },
+            "-o" | "--only-matching" => { builder.only_matching(true); },
+            "-b" | "--byte-offset" => { builder.byte_offset(true); },
+            "--column" => { builder.column(true); },
+            "-q" | "--quiet" => { builder.quiet(true); },
+            "-l" | "--files-with-matches" => { builder.files_with_matches(true); },
+            "-L" | "--files-without-match" => { builder.files_without_match(true); },
+            "-Z" | "--null" => { builder.null(true); },
+            "--line-null" => { builder.line_null(true); },
+            "--total" => { builder.total(true); },
+            "--progress" => { builder.progress(true); },
+            "--summary" => { builder.summary(true); },
+            "--no-group-separator" => { builder.no_group_separator(true); },
+            "--no-separator" => { builder.no_separator(true); },
+            "--reverse-match" => { builder.reverse_match(true); },
+            "--passthrough" => { builder.passthrough(true); },
+            "--no-merge-context" | "--window" => { builder.no_merge_context(true); },
+            "--name" => { builder.name(true); },
+            "--explain" | "--dry-run" => { builder.explain(true); },
+            "-a" | "--text" => { builder.binary_mode(BinaryMode::Text); },
+            "-I" => { builder.binary_mode(BinaryMode::WithoutMatch); },
+            "--binary-files" => {
+                let value: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                match value.as_str() {
+                    "binary" => { builder.binary_mode(BinaryMode::Binary); },
+                    "text" => { builder.binary_mode(BinaryMode::Text); },
+                    "without-match" => { builder.binary_mode(BinaryMode::WithoutMatch); },
+                    _ => return Err(MinigrepError::InvalidValue(flag, value)),
+                };
+            },
+            "-e" | "--pattern" => {
+                queries.push(parse_value(arg, next_value(&mut value, &mut args))?);
+            },
+            "--any" => {
+                let list: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                queries.extend(split_terms(&list));
+            },
+            "-f" | "--file" => {
+                let pattern_file: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.pattern_file(&pattern_file);
+            },
+            "--style" => {
+                let value: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                let styles = value.split(',')
+                                  .map(|style| style.trim().parse::<u8>())
+                                  .collect::<Result<Vec<u8>, _>>();
+                match styles {
+                    Ok(styles) if styles.iter().all(|&style| style::is_valid_style(style)) => {
+                        builder.styles(&styles);
+                    },
+                    _ => return Err(MinigrepError::InvalidValue(flag, value)),
+                };
+            },
+            "--timeout" => {
+                builder.timeout(parse_value(arg, next_value(&mut value, &mut args))?);
+            },
+            "--expand-tabs" => {
+                // the width is optional, hence may only be given after `=`
+                let width = match value.take() {
+                    Some(width) => parse_value::<NonZeroUsize>(arg, Some(width))?.get(),
+                    None => 8,
+                };
+                builder.expand_tabs(width);
+            },
+            "--max-line-length" => {
+                let n: NonZeroUsize = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.max_line_length(n.get());
+            },
+            "--field" => {
+                let n: NonZeroUsize = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.field(n.get());
+            },
+            "--field-sep" => {
+                let separator: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                if separator.is_empty() {
+                    return Err(MinigrepError::InvalidValue(flag, separator));
+                }
+                builder.field_separator(&separator);
+            },
+            "--threads" => {
+                let threads: NonZeroUsize = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.threads(threads.get());
+            },
+            "--byte-start" => {
+                builder.byte_start(parse_value(arg, next_value(&mut value, &mut args))?);
+            },
+            "--byte-end" => {
+                builder.byte_end(parse_value(arg, next_value(&mut value, &mut args))?);
+            },
+            "--include" => {
+                let pattern: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.include(&pattern);
+            },
+            "--exclude" => {
+                let pattern: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.exclude(&pattern);
+            },
+            "--max-depth" => {
+                builder.max_depth(parse_value(arg, next_value(&mut value, &mut args))?);
+            },
+            "-m" | "--max-count" => {
+                builder.max_count(parse_value(arg, next_value(&mut value, &mut args))?);
+            },
+            "--color" => {
+                let value: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                match value.as_str() {
+                    "auto" => { builder.color(ColorChoice::Auto); },
+                    "always" => { builder.color(ColorChoice::Always); },
+                    "never" => { builder.color(ColorChoice::Never); },
+                    _ => match style::parse_hex(&value) {
+                        Ok(rgb) => { builder.highlight_color(rgb); },
+                        Err(e) => return Err(MinigrepError::InvalidColor(e)),
+                    },
+                };
+            },
+            "--color-match" | "--color-line" | "--highlight-bg" => {
+                let value: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                let rgb = style::parse_hex(&value).map_err(MinigrepError::InvalidColor)?;
+                match flag.as_str() {
+                    "--color-match" => { builder.highlight_color(rgb); },
+                    "--color-line" => { builder.line_color(rgb); },
+                    _ => { builder.highlight_bg(rgb); },
+                }
+            },
+            "--color-mode" => {
+                let value: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                match value.as_str() {
+                    "truecolor" => { builder.color_mode(ColorMode::TrueColor); },
+                    "256" => { builder.color_mode(ColorMode::Palette256); },
+                    "ansi" => { builder.color_mode(ColorMode::Ansi16); },
+                    _ => return Err(MinigrepError::InvalidValue(flag, value)),
+                };
+            },
+            "--group-separator" => {
+                let separator: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.group_separator(&separator);
+            },
+            "--context-separator" => {
+                let separator: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.context_separator(&separator);
+            },
+            "--replace" => {
+                let replacement: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.replace(&replacement);
+            },
+            "--files-from" => {
+                let list: String = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.files_from(&list);
+            },
+            "-B" | "--before-context" => {
+                builder.before(parse_value(arg, next_value(&mut value, &mut args))?);
+            },
+            "-A" | "--after-context" => {
+                builder.after(parse_value(arg, next_value(&mut value, &mut args))?);
+            },
+            "-C" | "--context" => {
+                let context = parse_value(arg, next_value(&mut value, &mut args))?;
+                builder.before(context).after(context);
+            },
+            _ => return Err(MinigrepError::UnknownFlag(flag)),
+        }
+        if value.is_some() {
+            return Err(MinigrepError::UnexpectedValue(flag));
+        }
+    }
+
+    // read the positional arguments
+    let mut args = positional.into_iter();
+    if queries.is_empty() && builder.config.pattern_files.is_empty() {
+        queries.extend(args.next());
+    }
+    for query in &queries {
+        builder.query(query);
+    }
+    let mut filenames = args.collect::<Vec<String>>();
+    if filenames.len() > 1 && filenames[filenames.len() - 1].chars().all(|c| c.is_ascii_digit()) {
+        let arg = filenames.pop().unwrap();
+        let warning = match arg.parse::<u8>() {
+            Ok(x) if style::is_valid_style(x) => {
+                builder.style(x);
+                None
+            },
+            Ok(x) => Some(MinigrepError::UnsupportedStyle(x)),
+            Err(_) => Some(MinigrepError::InvalidStyle(arg)),
+        };
+        if let Some(warning) = warning {
+            let styler = Styler::stdout(ColorChoice::Auto, ColorMode::default());
+            eprintln!("{}", styler.add_fg_named(format!("WARNING: {}", warning), Color::Yellow));
+        }
+    }
+    for filename in &filenames {
+        builder.filename(filename);
+    }
+    Ok(())
+}
+
+
+/// Split a comma-separated list of terms, in which `\,` stands for a comma inside a term
+///
+/// Empty terms are skipped, as they would match every line.
+fn split_terms(list: &str) -> Vec<String> {
+    let mut terms = Vec::<String>::new();
+    let mut term = String::new();
+    let mut chars = list.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&',') => {
+                term.push(',');
+                chars.next();
+            },
+            ',' => terms.push(std::mem::take(&mut term)),
+            _ => term.push(c),
+        }
+    }
+    terms.push(term);
+    terms.retain(|term| !term.is_empty());
+    terms
+}
+
+
+/// Take the value of a flag, given either after `=` or as the next argument
+fn next_value(value: &mut Option<String>, args: &mut impl Iterator<Item = String>) 
+    -> Option<String> {
+    match value.take() {
+        Some(v) => Some(v),
+        None => args.next(),
+    }
+}
+
+
+/// Parse the value following a flag
+///
+/// # Errors
+///
+/// * `MissingValue` if there is no value
+/// * `InvalidValue` if the value can not be parsed
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) 
+    -> Result<T, MinigrepError> {
+    let value = match value {
+        Some(v) => v,
+        None => return Err(MinigrepError::MissingValue(flag.to_string())),
+    };
+    match value.parse::<T>() {
+        Ok(x) => Ok(x),
+        Err(_) => Err(MinigrepError::InvalidValue(flag.to_string(), value)),
+    }
+}
+
+
+/// Read the content of a file, whose lines are given by `Contents::lines`
+///
+/// `filename` may be a `&str`, a `String`, a `&Path`, or a `PathBuf`. If it is `-`, the content
+/// is read from the standard input instead. Compressed files are decompressed.
+///
+/// The whole file is read at once; `run` reads the files line by line instead. With the `mmap` 
+/// feature, files which are not compressed are memory-mapped instead of being copied to memory,
+/// if possible; they must then not be modified while the result is in use.
+///
+/// # Errors
+///
+/// * `FileNotFound` if the file can not be opened
+/// * `NotReadable` if the file can be opened but not read
+/// * `Decompression` if the file is compressed but can not be decompressed; the error names the
+///   format given by the extension
+/// * `StdinNotReadable` if the standard input can not be read
+/// * `Binary` if the content is binary (see `is_binary`)
+/// * `InvalidUtf8` if the content is not valid UTF-8
+///
+/// The file names in the errors are those of `filename`, with any invalid UTF-8 replaced.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::PathBuf;
+/// use minigrep::read_file;
+///
+/// let contents = read_file(PathBuf::from("poem.txt")).unwrap();
+///
+/// assert_eq!("How public, like a frog", contents.lines()[6]);
+/// ```
+pub fn read_file(filename: impl AsRef<Path>) -> Result<Contents, MinigrepError> {
+    let path = filename.as_ref();
+    let name = path.to_string_lossy();
+    let filename: &str = &name;
+
+    #[cfg(feature = "mmap")]
+    {
+        if filename != "-" && !compression::is_compressed(filename) {
+            if let Some(contents) = map_file(path, filename)? {
+                return Ok(contents);
+            }
+        }
+    }
+
+    let mut content = Vec::<u8>::new();
+    if filename == "-" {
+        if io::stdin().read_to_end(&mut content).is_err() {
+            return Err(MinigrepError::StdinNotReadable);
+        }
+    } else {
+        let mut file = match File::open(path) {
+            Ok(f) => compression::reader(filename, f),
+            Err(_) => return Err(MinigrepError::FileNotFound(filename.to_string())),
+        };
+        if file.read_to_end(&mut content).is_err() {
+            if compression::is_compressed(filename) {
+                let format = compression::compression(filename).name();
+                return Err(MinigrepError::Decompression(filename.to_string(), format));
+            }
+            return Err(MinigrepError::NotReadable(filename.to_string()));
+        }
+    }
+    if is_binary(&content) {
+        return Err(MinigrepError::Binary(filename.to_string()));
+    }
+    match String::from_utf8(content) {
+        Ok(content) => Ok(Contents::Owned(content)),
+        Err(_) => Err(MinigrepError::InvalidUtf8(filename.to_string())),
+    }
+}
+
+
+/// Map a file to memory, or return `None` if it can not be mapped (for instance, if it is a pipe)
+///
+/// `filename` is the name of the file in the errors.
+///
+/// # Errors
+///
+/// * `FileNotFound` if the file can not be opened
+/// * `Binary` if the content is binary
+/// * `InvalidUtf8` if the content is not valid UTF-8
+#[cfg(feature = "mmap")]
+fn map_file(path: &Path, filename: &str) -> Result<Option<Contents>, MinigrepError> {
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return Err(MinigrepError::FileNotFound(filename.to_string())),
+    };
+    // SAFETY: the mapping is only valid as long as the file is not modified, which is documented
+    // in `read_file`
+    let map = match unsafe { memmap2::Mmap::map(&file) } {
+        Ok(map) => map,
+        Err(_) => return Ok(None),
+    };
+    if is_binary(&map) {
+        return Err(MinigrepError::Binary(filename.to_string()));
+    }
+    match Contents::mapped(map) {
+        Some(contents) => Ok(Some(contents)),
+        None => Err(MinigrepError::InvalidUtf8(filename.to_string())),
+    }
+}
+
+/// Number of bytes at the start of a file in which a NUL byte makes it binary
+pub const BINARY_CHUNK: usize = 8192;
+
+
+/// Whether content is binary, *i.e.*, has a NUL byte in its first `BINARY_CHUNK` bytes
+///
+/// # Example
+///
+/// ```
+/// assert!(minigrep::is_binary(b"ELF\0\x01"));
+/// assert!(!minigrep::is_binary("Rust:\nsafe, fast, productive.".as_bytes()));
+/// ```
+pub fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(BINARY_CHUNK).any(|&byte| byte == 0)
+}
+
+
+/// Split a string into lines
+///
+/// Lines may end with `\n` or `\r\n`. As for `grep`, a newline at the end of the string does 
+/// not start a new line; in particular, an empty string gives an empty vector.
+fn split_lines(content: &str) -> Vec<&str> {
+    let mut lines = content.split('\n').collect::<Vec<&str>>();
+    let last = lines.pop().unwrap_or("");
+    let mut res = Vec::<&str>::new();
+    // all the lines but the last one are followed by `\n`, hence may end with `\r\n`
+    for line in lines {
+        res.push(line.strip_suffix('\r').unwrap_or(line));
+    }
+    if !last.is_empty() {
+        res.push(last);
+    }
+    res
+}
+
+/// Format a string to highlight each occurrence of a word
+///
+/// The output is built in a single pass from left to right, so that the escape codes already 
+/// written are never searched again. The occurrences do not overlap: with `aa` in `aaaa`, the
+/// two halves are highlighted, one after the other.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::format; 
+///
+/// let line = "This is a fine sentence!";
+/// let word = "fine";
+/// let style = 2;
+/// 
+/// let formatted_line = format(line, word, style);
+///
+/// assert_eq!("This is a \x1b[2;1mfine\x1b[0m sentence!".to_string(), 
+///            formatted_line)
+/// ```
+pub fn format(line: &str, word: &str, style: u8) -> String {
+    format_with_count(line, word, style).0
+}
+
+/// Format a string to highlight each occurrence of a word, and count the occurrences
+///
+/// The line is walked from left to right and each non-overlapping occurrence is wrapped on its 
+/// own, as with `format`.
+///
+/// # Values
+///
+/// The formatted string and the number of occurrences highlighted
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::format_with_count; 
+///
+/// let (formatted_line, count) = format_with_count("a fine, fine line", "fine", 1);
+///
+/// assert_eq!("a \x1b[1;1mfine\x1b[0m, \x1b[1;1mfine\x1b[0m line".to_string(), formatted_line);
+/// assert_eq!(2, count);
+/// ```
+pub fn format_with_count(line: &str, word: &str, style: u8) -> (String, usize) {
+    let spans = find_matches(word, line, true);
+    (highlight(line, &spans, style), spans.len())
+}
+
+
+/// Wrap the given byte ranges of a line with a style, leaving the rest of the line untouched
+///
+/// The ranges may come from anywhere, such as the matches of a regular expression. They are
+/// wrapped from left to right in the order of their starts; the part of a range overlapping a 
+/// previous one is not wrapped again, and empty ranges are ignored. 
+///
+/// # Panics
+///
+/// If a range does not start and end on character boundaries of the line.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::highlight;
+///
+/// let highlighted = highlight("This is a fine sentence!", &[(10, 14), (0, 4)], 1);
+///
+/// assert_eq!("\x1b[1;1mThis\x1b[0m is a \x1b[1;1mfine\x1b[0m sentence!".to_string(), 
+///            highlighted)
+/// ```
+pub fn highlight(line: &str, spans: &[(usize, usize)], style: u8) -> String {
+    highlight_spans(line, spans, &[style], None)
+}
+
+
+/// Format a string to highlight each occurrence of a word with a style, ignoring the case
+///
+/// The occurrences keep their original case.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::format_case_insensitive;
+///
+/// let formatted_line = format_case_insensitive("‘Ductape’ is a typo", "duct", 1);
+///
+/// assert_eq!("‘\x1b[1;1mDuct\x1b[0mape’ is a typo".to_string(), formatted_line)
+/// ```
+pub fn format_case_insensitive(line: &str, word: &str, style: u8) -> String {
+    highlight_spans(line, &find_matches(word, line, false), &[style], None)
+}
+
+
+/// Format a string to highlight each occurrence of a word with several styles
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::format_styles;
+///
+/// let formatted_line = format_styles("This is a fine sentence!", "fine", &[1, 4]);
+///
+/// assert_eq!("This is a \x1b[1;4;1mfine\x1b[0m sentence!".to_string(), formatted_line)
+/// ```
+pub fn format_styles(line: &str, word: &str, styles: &[u8]) -> String {
+    highlight_spans(line, &find_matches(word, line, true), styles, None)
+}
+
+
+/// Format a string to highlight each occurrence of a word with a style and, optionally, an RGB
+/// colour
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::format_colored; 
+///
+/// let line = "This is a fine sentence!";
+/// 
+/// let formatted_line = format_colored(line, "fine", 1, Some((255, 0, 0)));
+///
+/// assert_eq!("This is a \x1b[38;2;255;0;0;1mfine\x1b[0m sentence!".to_string(), 
+///            formatted_line)
+/// ```
+pub fn format_colored(line: &str, word: &str, style: u8, color: Option<(u8, u8, u8)>) -> String {
+    highlight_spans(line, &find_matches(word, line, true), &[style], color)
+}
+
+
+/// Format a string to highlight each occurrence of a word with a background colour and, 
+/// optionally, a foreground colour, like a highlighter pen
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::format_background; 
+///
+/// let formatted_line = format_background("a fine line", "fine", (255, 255, 0), Some((0, 0, 0)));
+///
+/// assert_eq!("a \x1b[38;2;0;0;0;48;2;255;255;0mfine\x1b[0m line".to_string(), formatted_line)
+/// ```
+pub fn format_background(line: &str, word: &str, bg: (u8, u8, u8), fg: Option<(u8, u8, u8)>) 
+    -> String {
+    highlight_line(Styler::default(), line, &find_matches(word, line, true), &[], fg, Some(bg), 
+                   None)
+}
+
+
+/// Format a string to highlight each occurrence of any of several words with a style
+///
+/// Occurrences of different words which overlap are highlighted together.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::format_any;
+///
+/// let formatted_line = format_any("red and blue", &["blue", "red"], 4);
+///
+/// assert_eq!("\x1b[4;1mred\x1b[0m and \x1b[4;1mblue\x1b[0m".to_string(), formatted_line)
+/// ```
+pub fn format_any(line: &str, words: &[&str], style: u8) -> String {
+    highlight_spans(line, &Matcher::literals(words, CaseMode::Sensitive).find(line), &[style], None)
+}
+
+
+/// Wrap the given byte ranges of a line with styles and, if `color` is not `None`, a colour
+///
+/// The ranges are handled as by `highlight`.
+fn highlight_spans(line: &str, spans: &[(usize, usize)], styles: &[u8], 
+                   color: Option<(u8, u8, u8)>) -> String {
+    highlight_line(Styler::default(), line, spans, styles, color, None, None)
+}
+
+
+/// Wrap the given byte ranges of a line as `highlight_spans` does, with the background colour 
+/// `bg` if it is not `None`, and colour the rest of the line with `line_color` if it is not 
+/// `None`, the colours and styles being written by `styler`
+fn highlight_line(styler: Styler, line: &str, spans: &[(usize, usize)], styles: &[u8], 
+                  color: Option<(u8, u8, u8)>, bg: Option<(u8, u8, u8)>, 
+                  line_color: Option<(u8, u8, u8)>) -> String {
+    let rest = |part: &str| match line_color {
+        Some((r, g, b)) if !part.is_empty() => styler.add_fg_styles(part.to_string(), r, g, b, &[]),
+        _ => part.to_string(),
+    };
+    let mut spans = spans.to_vec();
+    spans.sort_unstable();
+    let mut res = String::with_capacity(line.len());
+    let mut last = 0;
+    for (start, end) in spans {
+        // the part already wrapped is skipped
+        let start = start.max(last);
+        if start >= end {
+            continue;
+        }
+        res.push_str(&rest(&line[last..start]));
+        let word = line[start..end].to_string();
+        match (color, bg) {
+            (Some((r, g, b)), None) => res.push_str(&styler.add_fg_styles(word, r, g, b, styles)),
+            (_, Some(_)) => res.push_str(&styler.add_colors_styles(word, color, bg, styles)),
+            (None, None) if styles.iter().any(|&style| style > 0) => {
+                res.push_str(&styler.add_styles(word, styles))
+            },
+            (None, None) => res.push_str(&word),
+        };
+        last = end;
+    }
+    res.push_str(&rest(&line[last..]));
+    res
+}
+
+
+/// Find the byte ranges `(start, end)` of the non-overlapping occurrences of the query in a line
+///
+/// The search resumes after the end of each occurrence found, so that occurrences never 
+/// overlap. If `case_sensitive` is `false`, both are compared in lowercase; the ranges still 
+/// refer to the original line and start and end on character boundaries.
+///
+/// This is the comparison used by all the case-insensitive searches of literal strings, both to
+/// select the lines and to highlight the matches, so that a selected line always has its matches
+/// highlighted. The lowercase is that of `char::to_lowercase`, character by character: `Ä` 
+/// matches `ä` and `ẞ` matches `ß`, but `SS` does not match `ß`, nor `I` the dotless `ı`.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::find_matches;
+///
+/// assert_eq!(vec![(0, 2), (2, 4)], find_matches("aa", "aaaa", true));
+/// assert_eq!(vec![(6, 10)], find_matches("rust", "Hello Rust!", false));
+/// ```
+pub fn find_matches(query: &str, line: &str, case_sensitive: bool) -> Vec<(usize, usize)> {
+    if case_sensitive {
+        return line.match_indices(query).map(|(i, m)| (i, i + m.len())).collect();
+    }
+    let (query, _) = to_lowercase_with_offsets(query);
+    let (lower, offsets) = to_lowercase_with_offsets(line);
+    let mut res = Vec::<(usize, usize)>::new();
+    for (i, m) in lower.match_indices(&query) {
+        let start = offsets[i];
+        let end = if m.is_empty() {
+            start
+        } else {
+            let last_char = offsets[i + m.len() - 1];
+            last_char + line[last_char..].chars().next().map_or(0, |c| c.len_utf8())
+        };
+        // several matches can fall within the same original character
+        match res.last() {
+            Some(&(_, last_end)) if start < last_end => (),
+            _ => res.push((start, end)),
+        }
+    }
+    res
+}
+
+
+/// Convert a string to lowercase, keeping track of where each byte comes from
+///
+/// The lowercase string is built character by character. The second element of the result 
+/// gives, for each byte of the lowercase string, the index in `s` of the character it comes 
+/// from, followed by the length of `s`.
+fn to_lowercase_with_offsets(s: &str) -> (String, Vec<usize>) {
+    let mut lower = String::with_capacity(s.len());
+    let mut offsets = Vec::<usize>::with_capacity(s.len() + 1);
+    for (i, c) in s.char_indices() {
+        for l in c.to_lowercase() {
+            lower.push(l);
+            offsets.extend(std::iter::repeat_n(i, l.len_utf8()));
+        }
+    }
+    offsets.push(s.len());
+    (lower, offsets)
+}
+
+
+/// Replace each run of whitespace in a string by a single space
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::normalize_whitespace;
+///
+/// assert_eq!(" foo bar ", normalize_whitespace("\tfoo   \n bar "));
+/// ```
+pub fn normalize_whitespace(s: &str) -> String {
+    normalize_whitespace_with_offsets(s).0
+}
+
+
+/// Replace each run of whitespace in a string by a single space, keeping track of where each 
+/// byte comes from
+///
+/// The second element of the result gives, for each byte of the normalized string, the index 
+/// in `s` of the character it comes from (the first of the run for a space), followed by the 
+/// length of `s`.
+fn normalize_whitespace_with_offsets(s: &str) -> (String, Vec<usize>) {
+    let mut normalized = String::with_capacity(s.len());
+    let mut offsets = Vec::<usize>::with_capacity(s.len() + 1);
+    let mut in_whitespace = false;
+    for (i, c) in s.char_indices() {
+        if c.is_whitespace() {
+            if !in_whitespace {
+                normalized.push(' ');
+                offsets.push(i);
+            }
+            in_whitespace = true;
+        } else {
+            normalized.push(c);
+            offsets.extend(std::iter::repeat_n(i, c.len_utf8()));
+            in_whitespace = false;
+        }
+    }
+    offsets.push(s.len());
+    (normalized, offsets)
+}
+
+
+/// Select the lines matched by a matcher, with the byte ranges of the matches
+fn search_with<S: AsRef<str>>(matcher: &Matcher, contents: &[S]) 
+    -> Vec<(usize, Vec<(usize, usize)>)> {
+    let mut res = Vec::<(usize, Vec<(usize, usize)>)>::new();
+    for (i, line) in contents.iter().enumerate() {
+        let spans = matcher.find(line.as_ref());
+        if !spans.is_empty() {
+            res.push((i, spans));
+        }
+    }
+    res
+}
+
+
+/// A line containing the query
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    /// number of the line, starting from 1
+    pub line_number: usize,
+    /// content of the line
+    pub text: String,
+    /// name of the file containing the line, if any
+    pub file: Option<String>,
+    /// byte ranges `(start, end)` of the matches in the line
+    pub spans: Vec<(usize, usize)>,
+}
+
+
+/// Select the lines containing the query
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::{search_all, Match};
+///
+/// let contents = vec!["Rust:".to_string(), "safe, fast, productive.".to_string()];
+/// let matches = search_all("rust", &contents, false);
+///
+/// assert_eq!(vec![Match { line_number: 1, text: "Rust:".to_string(), file: None, 
+///                         spans: vec![(0, 4)] }], 
+///            matches);
+/// ```
+pub fn search_all<S: AsRef<str>>(query: &str, contents: &[S], case_sensitive: bool) -> Vec<Match> {
+    search_with(&Matcher::literal(query, CaseMode::from(case_sensitive)), contents)
+        .into_iter()
+        .map(|(n_line, spans)| Match { line_number: n_line + 1, 
+                                       text: contents[n_line].as_ref().to_string(), 
+                                       file: None, spans })
+        .collect()
+}
+
+
+/// Select the indices of the strings containing the query, with the byte ranges `(start, end)` 
+/// of all its occurrences in each of them
+///
+/// The ranges do not overlap and start and end on character boundaries, as those given by
+/// `find_matches`; `search` and `search_case_insensitive` only keep their starts.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::search_with_positions;
+///
+/// let contents = ["‘Rust’ and rust", "safe, fast, productive."];
+///
+/// assert_eq!(vec![(0, vec![(3, 7), (15, 19)])], 
+///            search_with_positions("rust", &contents, false));
+/// ```
+pub fn search_with_positions<S: AsRef<str>>(query: &str, contents: &[S], case_sensitive: bool) 
+    -> Vec<(usize, Vec<(usize, usize)>)> {
+    search_with(&Matcher::literal(query, CaseMode::from(case_sensitive)), contents)
+}
+
+
+/// Select the indices of the strings containing any of the queries, with the byte offsets at 
+/// which the matches start in each of them
+///
+/// Overlapping matches of different queries count as one, starting at the first of them. The
+/// strings may be owned or borrowed, such as the lines given by `Contents::lines`.
+pub fn search<S: AsRef<str>>(queries: &[&str], contents: &[S]) -> Vec<(usize, Vec<usize>)> {
+    starts(search_with(&Matcher::literals(queries, CaseMode::Sensitive), contents))
+}
+
+
+/// Select the indices of the borrowed lines containing the query
+///
+/// Unlike `search`, it only returns the indices of the lines, and does not require owned data,
+/// which makes it suitable for benchmarks and for callers which already have borrowed lines.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::search_slices;
+///
+/// let contents = "Rust:\nsafe, fast, productive.\nTrust me.";
+/// let lines = contents.lines().collect::<Vec<&str>>();
+///
+/// assert_eq!(vec![2], search_slices("rust", &lines));
+/// ```
+pub fn search_slices(query: &str, lines: &[&str]) -> Vec<usize> {
+    search_with(&Matcher::literal(query, CaseMode::Sensitive), lines)
+        .into_iter()
+        .map(|(n_line, _)| n_line)
+        .collect()
+}
+
+
+/// Select the indices of the strings containing any of the queries, case-insensitive, with the
+/// byte offsets at which the matches start in each of them
+pub fn search_case_insensitive<S: AsRef<str>>(queries: &[&str], contents: &[S]) 
+    -> Vec<(usize, Vec<usize>)> {
+    starts(search_with(&Matcher::literals(queries, CaseMode::Insensitive), contents))
+}
+
+
+/// Select the indices of the strings matching a regular expression, with the byte offsets at 
+/// which each match starts in them
+///
+/// # Errors
+///
+/// * `InvalidRegex` if the pattern can not be compiled
+pub fn search_regex<S: AsRef<str>>(pattern: &str, contents: &[S]) 
+    -> Result<Vec<(usize, Vec<usize>)>, MinigrepError> {
+    Ok(starts(search_with(&Matcher::regex(pattern, CaseMode::Sensitive)?, contents)))
+}
+
+
+/// Check if a glob pattern matches anywhere in a text
+///
+/// `*` matches any sequence of characters, `?` any single character, and `[...]` any of the 
+/// characters between the brackets (or any other one with `[!...]`); the pattern can be anchored
+/// with `^` and `$`. The match is case-sensitive.
+///
+/// # Examples
+///
+/// ```
+/// use minigrep::glob_match;
+///
+/// assert!(glob_match("err*", "an error"));
+/// assert!(glob_match("err?o", "errno"));
+/// assert!(!glob_match("^err*", "an error"));
+/// ```
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    Matcher::globs(&[pattern], CaseMode::Sensitive).is_ok_and(|matcher| {
+        !matcher.find(text).is_empty()
+    })
+}
+
+
+/// Keep only the start of each range
+fn starts(matches: Vec<(usize, Vec<(usize, usize)>)>) -> Vec<(usize, Vec<usize>)> {
+    matches.into_iter()
+           .map(|(n_line, spans)| (n_line, spans.iter().map(|span| span.0).collect()))
+           .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn format_1() {
+        let sentence = "I love blue cheese!";
+        let word = "love";
+        let style: u8 = 1;
+        let sentence_highlighted = format(sentence, word, style);
+        let expected_result = "I \x1b[1;1mlove\x1b[0m blue cheese!".to_string();
+        assert_eq!(expected_result, sentence_highlighted);
+    }
+
+    #[test]
+    fn format_case_insensitive_1() {
+        assert_eq!("\x1b[4;1mRust\x1b[0m and \x1b[4;1mrUST\x1b[0m".to_string(),
+                   format_case_insensitive("Rust and rUST", "rust", 4));
+        assert_eq!("Rust and rUST".to_string(), format("Rust and rUST", "rust", 4));
+    }
+
+    #[test]
+    fn search_1() {
+        let query = "duct".to_string();
+        let contents = vec!["Rust:".to_string(),
+                            "safe, fast, productive.".to_string(), 
+                            "Pick three.".to_string(),
+                            "‘Ductape’ is a typo".to_string()];
+        assert_eq!(vec![(1, vec![15])], search(&[&query], &contents));
+    }
+
+    #[test]
+    fn search_2() {
+        let query = "duct".to_string();
+        let contents = vec!["Rust:".to_string(),
+                            "safe, fast, productive.".to_string(), 
+                            "Pick three.".to_string(),
+                            "‘Ductape’ is a typo".to_string()];
+        assert_eq!(vec![(1, vec![15]), (3, vec![3])], search_case_insensitive(&[&query], &contents));
+    }
+
+    #[test]
+    fn search_3() {
+        let query = "ss".to_string();
+        let contents = vec!["Straße, Strasse".to_string(), "İstanbul".to_string()];
+        assert_eq!(vec![(0, vec![13])], search(&[&query], &contents));
+        assert_eq!(vec![(0, vec![13])], search_case_insensitive(&[&query], &contents));
+        let query = "i".to_string();
+        assert_eq!(vec![(1, vec![0])], search_case_insensitive(&[&query], &contents));
+    }
+
+    #[test]
+    fn search_slices_1() {
+        let contents = String::from("safe, fast, productive.\nPick three.\nfast again");
+        let lines = contents.lines().collect::<Vec<&str>>();
+        assert_eq!(vec![0, 2], search_slices("fast", &lines));
+        assert_eq!(Vec::<usize>::new(), search_slices("Fast", &lines));
+        let owned = lines.iter().map(|line| line.to_string()).collect::<Vec<String>>();
+        let starts = search(&["fast"], &owned).into_iter().map(|(i, _)| i).collect::<Vec<usize>>();
+        assert_eq!(search_slices("fast", &lines), starts);
+    }
+
+    #[test]
+    fn search_with_positions_1() {
+        let contents = ["aaaa", "Ductape: dûct, DUCT", "no match"];
+        assert_eq!(vec![(0, vec![(0, 2), (2, 4)])], search_with_positions("aa", &contents, true));
+        assert_eq!(vec![(1, vec![(0, 4), (16, 20)])], 
+                   search_with_positions("duct", &contents, false));
+        assert_eq!(vec![(1, vec![(9, 14)])], search_with_positions("dûct", &contents, true));
+        assert_eq!(starts(search_with_positions("duct", &contents, false)),
+                   search_case_insensitive(&["duct"], &contents));
+    }
+
+    #[test]
+    fn search_regex_1() {
+        let contents = vec!["The colour".to_string(), "of the".to_string(), "color".to_string()];
+        assert_eq!(Ok(vec![(0, vec![4]), (2, vec![0])]), search_regex("colou?r", &contents));
+    }
+
+    #[test]
+    fn glob_match_anywhere() {
+        assert!(glob_match("err*", "error"));
+        assert!(glob_match("err*", "errno"));
+        assert!(glob_match("err*", "an error"));
+        assert!(!glob_match("err*", "an eror"));
+        assert!(glob_match("e[!x]ror$", "an error"));
+        assert!(!glob_match("^error$", "an error"));
+    }
+
+    #[test]
+    fn find_matches_non_overlapping() {
+        assert_eq!(vec![(0, 2), (2, 4)], find_matches("aa", "aaaa", true));
+        assert_eq!(vec![(0, 2), (2, 4)], find_matches("aa", "aaaaa", true));
+        assert_eq!(vec![(0, 2), (2, 4)], find_matches("AA", "aAaa", false));
+        assert!(find_matches("ab", "aaaa", true).is_empty());
+    }
+
+    #[test]
+    fn find_matches_multi_byte() {
+        assert_eq!(vec![(0, 2)], find_matches("i", "İstanbul", false));
+        assert_eq!(vec![(3, 7)], find_matches("duct", "‘Ductape’", false));
+        assert_eq!(vec![(0, 2), (2, 4)], find_matches("aa", "aaaaa", true));
+    }
+
+    #[test]
+    fn unicode_case_pairs() {
+        let pairs = [("ä", "ÄRGER"), ("Ä", "ärger"), ("ω", "ΩMEGA"), ("straße", "STRAẞE"), 
+                     ("İZMIR", "İzmir")];
+        for (query, line) in pairs {
+            // the line is selected, and the match highlighted, in the same way for the literal 
+            // strings and the regular expressions
+            let spans = find_matches(query, line, false);
+            assert_eq!(vec![(0, vec![spans[0].0])], search_case_insensitive(&[query], &[line]));
+            assert_eq!(spans, Matcher::regex(query, CaseMode::Insensitive).unwrap().find(line));
+            let highlighted = highlight_spans(line, &spans, &[1], None);
+            assert_eq!(format!("\x1b[1;1m{}\x1b[0m{}", &line[..spans[0].1], &line[spans[0].1..]),
+                       highlighted);
+        }
+        for (query, line) in [("SS", "ß"), ("I", "ırmak")] {
+            assert!(find_matches(query, line, false).is_empty());
+            assert!(search_case_insensitive(&[query], &[line]).is_empty());
+        }
+    }
+
+    #[test]
+    fn highlight_spans_1() {
+        let line = "‘Ductape’ is a typo";
+        let spans = find_matches("duct", line, false);
+        assert_eq!("‘\x1b[1;1mDuct\x1b[0mape’ is a typo".to_string(), 
+                   highlight_spans(line, &spans, &[1], None));
+        assert_eq!("‘\x1b[38;2;255;136;0mDuct\x1b[0mape’ is a typo".to_string(), 
+                   highlight_spans(line, &spans, &[0], Some((255, 136, 0))));
+        assert_eq!("‘\x1b[38;2;255;136;0;4mDuct\x1b[0mape’ is a typo".to_string(), 
+                   highlight_spans(line, &spans, &[4], Some((255, 136, 0))));
+        assert_eq!("‘\x1b[38;2;255;136;0;1;4mDuct\x1b[0mape’ is a typo".to_string(), 
+                   highlight_spans(line, &spans, &[1, 4], Some((255, 136, 0))));
+        assert_eq!("‘\x1b[1;4;1mDuct\x1b[0mape’ is a typo".to_string(), 
+                   highlight_spans(line, &spans, &[1, 4], None));
+        assert_eq!(line.to_string(), highlight_spans(line, &spans, &[], None));
+    }
+
+    #[test]
+    fn replace_spans_1() {
+        let line = "Duct tape, duct and ductile";
+        let matcher = Matcher::literal("duct", CaseMode::Insensitive);
+        assert_eq!(("pipe tape, pipe and pipeile".to_string(), vec![(0, 4), (11, 15), (20, 24)]),
+                   replace_spans(line, &matcher.find(line), "pipe"));
+        let matcher = Matcher::literal("duct", CaseMode::Sensitive);
+        assert_eq!(("Duct tape, x and xile".to_string(), vec![(11, 12), (17, 18)]),
+                   replace_spans(line, &matcher.find(line), "x"));
+        assert_eq!(("abc".to_string(), vec![]), replace_spans("abc", &[(1, 1)], "x"));
+    }
+
+    #[test]
+    fn expand_tabs_1() {
+        assert_eq!(("a       b".to_string(), vec![(8, 9)]), expand_tabs("a\tb", &[(2, 3)], 8));
+        assert_eq!(("abcd    |".to_string(), vec![(3, 8)]), expand_tabs("abcd\t|", &[(3, 5)], 4));
+        // a tab at a tab stop is a whole tab
+        assert_eq!(("ab  ".to_string(), vec![]), expand_tabs("ab\t", &[], 2));
+        assert_eq!(("é   x".to_string(), vec![(0, 2), (5, 6)]), 
+                   expand_tabs("é\tx", &[(0, 2), (3, 4)], 4));
+        assert_eq!(("none".to_string(), vec![(0, 4)]), expand_tabs("none", &[(0, 4)], 8));
+    }
+
+    #[test]
+    fn truncate_line_1() {
+        let line = "0123456789";
+        assert_eq!((line.to_string(), vec![(1, 2)]), truncate_line(line, &[(1, 2)], 10));
+        assert_eq!(("0123…".to_string(), vec![(1, 2)]), truncate_line(line, &[(1, 2)], 4));
+        // a match which would be cut off is centred
+        assert_eq!(("…5678…".to_string(), vec![(4, 6)]), truncate_line(line, &[(6, 8)], 4));
+        assert_eq!(("…6789".to_string(), vec![(5, 6)]), truncate_line(line, &[(8, 9)], 4));
+        // a match longer than the line kept starts it
+        assert_eq!(("…345…".to_string(), vec![(3, 6)]), truncate_line(line, &[(3, 9)], 3));
+        // the length is counted in characters
+        assert_eq!(("éàü…".to_string(), vec![(2, 6)]), truncate_line("éàüö", &[(2, 6)], 3));
+        assert_eq!(("…àüö".to_string(), vec![(7, 9)]), truncate_line("éàüö", &[(6, 8)], 3));
+    }
+
+    #[test]
+    fn format_overlapping_query() {
+        assert_eq!("\x1b[1;1maa\x1b[0m\x1b[1;1maa\x1b[0m", format("aaaa", "aa", 1));
+        assert_eq!("\x1b[1;1maa\x1b[0m\x1b[1;1maa\x1b[0ma", format("aaaaa", "aa", 1));
+        assert_eq!(("b\x1b[1;1maa\x1b[0m\x1b[1;1maa\x1b[0mb".to_string(), 2), 
+                   format_with_count("baaaab", "aa", 1));
+    }
+
+    #[test]
+    fn format_single_character() {
+        assert_eq!("b\x1b[2;1ma\x1b[0mn\x1b[2;1ma\x1b[0mn\x1b[2;1ma\x1b[0m", 
+                   format("banana", "a", 2));
+        // `m` and `1` appear in the escape codes, which are not searched again
+        assert_eq!("\x1b[1;1mm\x1b[0mo\x1b[1;1mm\x1b[0m", format("mom", "m", 1));
+        assert_eq!("\x1b[1;1m1\x1b[0m+\x1b[1;1m1\x1b[0m", format("1+1", "1", 1));
+    }
+
+    #[test]
+    fn format_three_occurrences() {
+        let (formatted, count) = format_with_count("duct, Duct, duct and duct", "duct", 4);
+        assert_eq!("\x1b[4;1mduct\x1b[0m, Duct, \x1b[4;1mduct\x1b[0m and \x1b[4;1mduct\x1b[0m", 
+                   formatted);
+        assert_eq!(3, count);
+        assert_eq!(("no match".to_string(), 0), format_with_count("no match", "duct", 4));
+    }
+
+    #[test]
+    fn highlight_1() {
+        assert_eq!("\x1b[4;1mab\x1b[0mc\x1b[4;1mde\x1b[0m", 
+                   highlight("abcde", &[(3, 5), (0, 2), (2, 2)], 4));
+        // overlapping ranges are not wrapped twice
+        assert_eq!("\x1b[1;1mabc\x1b[0m\x1b[1;1md\x1b[0me", 
+                   highlight("abcde", &[(1, 4), (0, 3)], 1));
+        assert_eq!("é\x1b[1;1mtoile\x1b[0m", highlight("étoile", &[(2, 7)], 1));
+    }
+
+    #[test]
+    fn highlight_line_colors() {
+        let line = "a frog, a bog";
+        let spans = [(2, 6), (10, 13)];
+        let styler = Styler::default();
+        assert_eq!(highlight_spans(line, &spans, &[1], Some((255, 0, 0))), 
+                   highlight_line(styler, line, &spans, &[1], Some((255, 0, 0)), None, None));
+        assert_eq!("\x1b[38;2;0;0;255ma \x1b[0m\x1b[38;2;255;0;0;1mfrog\x1b[0m\
+                    \x1b[38;2;0;0;255m, a \x1b[0m\x1b[38;2;255;0;0;1mbog\x1b[0m",
+                   highlight_line(styler, line, &spans, &[1], Some((255, 0, 0)), None, 
+                                  Some((0, 0, 255))));
+        assert_eq!("\x1b[38;2;0;0;255ma \x1b[0m\x1b[4;1mfrog\x1b[0m\
+                    \x1b[38;2;0;0;255m, a bog\x1b[0m",
+                   highlight_line(styler, line, &spans[..1], &[4], None, None, Some((0, 0, 255))));
+        // dark text on a yellow background, or only the background
+        assert_eq!("a \x1b[38;2;0;0;0;48;2;255;255;0mfrog\x1b[0m, a bog", 
+                   highlight_line(styler, line, &spans[..1], &[], Some((0, 0, 0)), 
+                                  Some((255, 255, 0)), None));
+        assert_eq!("a \x1b[48;2;255;255;0;4mfrog\x1b[0m, a bog", 
+                   highlight_line(styler, line, &spans[..1], &[4], None, Some((255, 255, 0)), None));
+    }
+
+    #[test]
+    fn highlight_spans_modes() {
+        // the highlighted spans are those which select the line
+        let line = "productive, the Duct_ duct.";
+        let matcher = Matcher::literal("duct", CaseMode::Insensitive).whole_words(true).unwrap();
+        assert_eq!("productive, the Duct_ \x1b[1;1mduct\x1b[0m.".to_string(),
+                   highlight_spans(line, &matcher.find(line), &[1], None));
+        let matcher = Matcher::regex("[dD]uct\\b", CaseMode::Sensitive).unwrap();
+        assert_eq!("productive, the Duct_ \x1b[1;1mduct\x1b[0m.".to_string(),
+                   highlight_spans(line, &matcher.find(line), &[1], None));
+    }
+
+    #[test]
+    fn parse_value_1() {
+        assert_eq!(Ok(3), parse_value::<usize>("-A", Some("3".to_string())));
+        assert_eq!(Err(MinigrepError::MissingValue("-A".to_string())), 
+                   parse_value::<usize>("-A", None));
+        assert_eq!(Err(MinigrepError::InvalidValue("-A".to_string(), "x".to_string())), 
+                   parse_value::<usize>("-A", Some("x".to_string())));
+    }
+
+    #[test]
+    fn lines_with_offsets_1() {
+        let lines = lines_with_offsets("ab\r\nc\n\néd".as_bytes(), false).map(Result::unwrap)
+                                                                  .collect::<Vec<_>>();
+        assert_eq!(vec![(0, "ab".to_string()), (4, "c".to_string()), (6, String::new()),
+                        (7, "éd".to_string())], 
+                   lines);
+        assert!(lines_with_offsets(&b"a\n\xFF\n"[..], false).any(|line| line.is_err()));
+        assert_eq!(vec![(0, "a".to_string()), (2, "\u{FFFD}".to_string())],
+                   lines_with_offsets(&b"a\n\xFF\n"[..], true).map(Result::unwrap)
+                                                             .collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn char_column_1() {
+        let line = "‘Ductape’ is a typo";
+        assert_eq!(1, char_column(line, 0));
+        assert_eq!(2, char_column(line, find_matches("duct", line, false)[0].0));
+    }
+
+    #[test]
+    fn json_line_1() {
+        assert_eq!("{\"line\": 42, \"text\": \"a \\\"b\\\" \\\\ c\\t\", \"file\": \"poem.txt\"}", 
+                   json_line("poem.txt", 41, "a \"b\" \\ c\t"));
+        assert_eq!("\"\\u0001\"", json_string("\u{1}"));
+    }
+
+    #[test]
+    fn csv_line_1() {
+        assert_eq!("42,\"a, \"\"b\"\"\"", csv_line(None, 41, "a, \"b\""));
+        assert_eq!("\"poem.txt\",1,\"\"", csv_line(Some("poem.txt"), 0, ""));
+    }
+
+    /// arguments for Config::new, preceded by the name of the program
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        let mut all = vec!["minigrep".to_string()];
+        all.extend(args.iter().map(|arg| arg.to_string()));
+        all.into_iter()
+    }
+
+    #[test]
+    fn config_missing_query() {
+        assert_eq!(Err(MinigrepError::MissingQuery), Config::new(args(&[])));
+        assert_eq!(Err(MinigrepError::MissingQuery), Config::new(args(&["-n", "--"])));
+    }
+
+    #[test]
+    fn config_missing_filename() {
+        assert_eq!(Err(MinigrepError::MissingFilename), Config::new(args(&["you"])));
+        assert_eq!(Err(MinigrepError::MissingFilename), Config::new(args(&["-i", "you", "-n"])));
+    }
+
+    #[test]
+    fn config_style() {
+        let config = Config::new(args(&["you", "poem.txt", "3"])).unwrap();
+        assert_eq!(vec![3], config.styles);
+        assert_eq!(vec!["poem.txt".to_string()], config.filenames);
+        let config = Config::new(args(&["you", "3"])).unwrap();
+        assert!(config.styles.is_empty());
+        assert_eq!(vec!["3".to_string()], config.filenames);
+        // unsupported styles are ignored, with a warning
+        let config = Config::new(args(&["you", "poem.txt", "6"])).unwrap();
+        assert!(config.styles.is_empty());
+        assert_eq!(vec!["poem.txt".to_string()], config.filenames);
+        let config = Config::new(args(&["-n", "you", "poem.txt", "-", "1", "--color=never"]));
+        assert_eq!(Config::builder().query("you").filename("poem.txt").filename("-").style(1)
+                                    .line_numbers(true).color(ColorChoice::Never).build(),
+                   config);
+    }
+
+    #[test]
+    fn config_style_list() {
+        let config = Config::new(args(&["--style", "1,4", "you", "poem.txt"])).unwrap();
+        assert_eq!(vec![1, 4], config.styles);
+        let config = Config::new(args(&["--style=3", "you", "poem.txt", "-", "2"])).unwrap();
+        assert_eq!(vec![2], config.styles);
+        assert_eq!(Err(MinigrepError::InvalidValue("--style".to_string(), "1,x".to_string())),
+                   Config::new(args(&["--style", "1,x", "you", "poem.txt"])));
+        assert_eq!(Err(MinigrepError::InvalidValue("--style".to_string(), "1,10".to_string())),
+                   Config::new(args(&["--style", "1,10", "you", "poem.txt"])));
+    }
+
+    #[test]
+    fn config_case() {
+        let config = Config::new(args(&["-i", "--smart-case", "you", "poem.txt"])).unwrap();
+        assert_eq!(CaseMode::Smart, config.case);
+        let config = Config::new(args(&["--smart-case", "-s", "you", "poem.txt"])).unwrap();
+        assert_eq!(CaseMode::Sensitive, config.case);
+    }
+
+    #[test]
+    fn config_filename() {
+        let config = Config::new(args(&["-h", "-H", "you", "poem.txt"])).unwrap();
+        assert!(config.with_filename && !config.suppress_filename);
+        let config = Config::new(args(&["-H", "you", "poem.txt", "-h"])).unwrap();
+        assert!(!config.with_filename && config.suppress_filename);
+    }
+
+    #[test]
+    fn config_flags() {
+        assert_eq!(Err(MinigrepError::UnknownFlag("--nope".to_string())),
+                   Config::new(args(&["--nope", "you", "poem.txt"])));
+        assert_eq!(Err(MinigrepError::UnexpectedValue("--count".to_string())),
+                   Config::new(args(&["--count=2", "you", "poem.txt"])));
+        assert_eq!(Err(MinigrepError::MissingValue("-A".to_string())),
+                   Config::new(args(&["you", "poem.txt", "-A"])));
+        assert_eq!(Err(MinigrepError::ConflictingFlags("-l".to_string(), "-L".to_string())),
+                   Config::new(args(&["-L", "you", "poem.txt", "-l"])));
+        let config = Config::new(args(&["-C", "2", "--", "-you", "poem.txt"])).unwrap();
+        assert_eq!(vec!["-you".to_string()], config.queries);
+        assert_eq!((2, 2), (config.before, config.after));
+        let config = Config::new(args(&["-I", "--binary-files=text", "you", "poem.txt"])).unwrap();
+        assert_eq!(BinaryMode::Text, config.binary_mode);
+        assert_eq!(Err(MinigrepError::InvalidValue("--binary-files".to_string(), "no".to_string())),
+                   Config::new(args(&["--binary-files", "no", "you", "poem.txt"])));
+        let config = Config::new(args(&["--color-mode=256", "you", "poem.txt"])).unwrap();
+        assert_eq!(ColorMode::Palette256, config.color_mode);
+        assert_eq!(Err(MinigrepError::InvalidValue("--color-mode".to_string(), "88".to_string())),
+                   Config::new(args(&["--color-mode", "88", "you", "poem.txt"])));
+    }
+
+    #[test]
+    fn config_reverse_match() {
+        let config = Config::new(args(&["--color", "ff8800", "--reverse-match", "--highlight-bg=00f", 
+                                        "--style=1,4", "you", "poem.txt"])).unwrap();
+        assert_eq!((vec![7], None, None), 
+                   (config.styles.clone(), config.highlight_color, config.highlight_bg));
+        let config = Config::new(args(&["--reverse-match", "you", "poem.txt", "1"])).unwrap();
+        assert_eq!(vec![7], config.styles);
+        assert_eq!("a \x1b[7;1mfrog\x1b[0m", 
+                   highlight_line(Styler::default(), "a frog", &[(2, 6)], &config.styles, 
+                                  config.highlight_color, config.highlight_bg, None));
+    }
+
+    #[test]
+    fn print_line_prefixes() {
+        let config = Config::new(args(&["-n", "--column", "--count-per-line", "o", "f"])).unwrap();
+        let mut out = Vec::<u8>::new();
+        print_line(&config, None, 2, 0, Some(4), Some(2), "foo", ':', &mut out).unwrap();
+        print_line(&config, None, 3, 0, None, None, "bar", '-', &mut out).unwrap();
+        // the number of occurrences is the last prefix, separated as the others
+        let config = Config::new(args(&["--count-per-line", "-Z", "o", "f"])).unwrap();
+        print_line(&config, Some("f"), 2, 0, None, Some(2), "foo", '|', &mut out).unwrap();
+        assert_eq!("3:4:2:foo\n4-bar\nf\x002|foo\n", String::from_utf8_lossy(&out));
+    }
+
+    #[test]
+    fn config_include_exclude() {
+        let config = Config::new(args(&["-r", "--include", "*.rs", "--exclude=main.*", 
+                                        "--include=*.toml", "you", "."])).unwrap();
+        assert_eq!(vec!["*.rs".to_string(), "*.toml".to_string()], config.include);
+        assert_eq!(vec!["main.*".to_string()], config.exclude);
+    }
+
+    #[test]
+    fn search_reader_field() {
+        let config = Config::new(args(&["--field-sep", ",", "--field=2", "--style=4", 
+                                        "--color=always", "error", "-"])).unwrap();
+        let matcher = build_matcher(&config, &["error"]).unwrap();
+        let contents = "error,ok,error\n1,error\n2\n";
+        let mut out = Vec::<u8>::new();
+        assert_eq!(Ok(1), search_reader(&config, &matcher, None, "-", contents.as_bytes(), 
+                                        &SearchState::new(None), &mut out));
+        // the match is highlighted in the whole line; the lines with a single field do not match
+        assert_eq!("1,\x1b[4;1merror\x1b[0m\n", String::from_utf8(out).unwrap());
+        assert_eq!(Err(MinigrepError::InvalidValue("--field".to_string(), "0".to_string())),
+                   Config::new(args(&["--field", "0", "error", "-"])));
+        assert_eq!("\t", Config::new(args(&["error", "-"])).unwrap().field_separator);
+    }
+
+    #[test]
+    fn rank_files_1() {
+        let dir = std::env::temp_dir().join(format!("minigrep_rank_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in [("a.txt", "frog\n"), ("b.txt", "frog\nfrog, frog\n"), 
+                                ("c.txt", "bog\n"), ("d.txt", "frog\n")] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        let rank = |flag: &str| {
+            let config = Config::new(args(&[flag, "--color=always", "-r", "frog", 
+                                            dir.to_str().unwrap()])).unwrap();
+            let matcher = build_matcher(&config, &["frog"]).unwrap();
+            let mut out = Vec::<u8>::new();
+            let result = rank_files(&config, &matcher, &list_inputs(&config, &config.filenames), 
+                                    &SearchState::new(None), &mut out);
+            (result, String::from_utf8(out).unwrap())
+        };
+        let ranked = rank("--rank");
+        let all = rank("--rank-all");
+        fs::remove_dir_all(&dir).unwrap();
+
+        // ties are sorted by name, and the files without selected lines are only kept with 
+        // `rank_all`
+        let line = |name: &str, n: usize| {
+            let name = dir.join(name).display().to_string();
+            format!("{}:{}\n", style::add_fg_named(name, Color::Magenta), n)
+        };
+        let expected = line("b.txt", 2) + &line("a.txt", 1) + &line("d.txt", 1);
+        assert_eq!((Ok((4, 3)), expected.clone()), ranked);
+        assert_eq!((Ok((4, 3)), expected + &line("c.txt", 0)), all);
+        assert_eq!(Err(MinigrepError::UnreadableFiles(1)), 
+                   rank_files(&Config::new(args(&["--rank", "you", "src"])).unwrap(), 
+                              &Matcher::literal("you", CaseMode::Sensitive), 
+                              &[Input::Directory("src".to_string())], &SearchState::new(None), 
+                              &mut Vec::<u8>::new()));
+    }
+
+    #[test]
+    fn search_reader_windows() {
+        let contents = "a frog\nb\nc frog\nd\ne\nf\ng frog\n";
+        let search = |flags: &[&str]| {
+            let mut all = flags.to_vec();
+            all.extend(["frog", "-"]);
+            let config = Config::new(args(&all)).unwrap();
+            let matcher = build_matcher(&config, &["frog"]).unwrap();
+            let mut out = Vec::<u8>::new();
+            search_reader(&config, &matcher, None, "-", contents.as_bytes(), 
+                          &SearchState::new(None), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!("a frog\nb\nc frog\nd\n--\nf\ng frog\n", search(&["-C", "1"]));
+        // each block has its own context, the selected lines in it included
+        assert_eq!("a frog\nb\n--\nb\nc frog\nd\n--\nf\ng frog\n", 
+                   search(&["-C", "1", "--no-merge-context"]));
+        assert_eq!("a frog\nb\nc frog\n--\nc frog\nd\ne\n--\ng frog\n", 
+                   search(&["-A", "2", "--window", "--context-separator=--"]));
+        assert_eq!("a frog\nb\nc frog\n", 
+                   search(&["-A", "2", "--window", "--no-separator", "-m", "1"]));
+    }
+
+    #[test]
+    fn collect_matches_1() {
+        let config = Config::new(args(&["-i", "-m", "1", "the", "poem.txt", "tests/fixtures/fox.txt"]))
+                           .unwrap();
+        let matches = collect_matches(&config).unwrap();
+        assert_eq!(vec![(Some("poem.txt"), 3), (Some("tests/fixtures/fox.txt"), 1)],
+                   matches.iter().map(|m| (m.file.as_deref(), m.line_number)).collect::<Vec<_>>());
+        assert_eq!(vec![(0, 3)], matches[1].spans);
+
+        let config = Config::new(args(&["-v", "o", "tests/fixtures/fox.txt"])).unwrap();
+        assert_eq!(Vec::<Match>::new(), collect_matches(&config).unwrap());
+        let config = Config::new(args(&["-v", "fox", "tests/fixtures/fox.txt"])).unwrap();
+        let matches = collect_matches(&config).unwrap();
+        assert_eq!(vec!["jumps over", "the lazy dog."], 
+                   matches.iter().map(|m| m.text.as_str()).collect::<Vec<&str>>());
+        assert!(matches.iter().all(|m| m.spans.is_empty()));
+
+        let config = Config::new(args(&["you", "src"])).unwrap();
+        assert!(collect_matches(&config).is_err());
+    }
+
+    #[test]
+    fn explanation_1() {
+        let config = Config::new(args(&["-i", "--style=1,4", "-e", "you", "-e", "a\"b", 
+                                        "poem.txt", "src"])).unwrap();
+        let inputs = list_inputs(&config, &config.filenames);
+        let text = explanation(&config, &["you", "a\"b"], &inputs);
+        for expected in ["\"you\", \"a\\\"b\"", "fixed strings", "insensitive", "1, 4", 
+                         "Files:", "  poem.txt\n", "  src (directory, skipped without -r)\n"] {
+            assert!(text.contains(expected), "{}", expected);
+        }
+
+        let config = Config::new(args(&["-r", "-E", "you", "src"])).unwrap();
+        let inputs = list_inputs(&config, &config.filenames);
+        let text = explanation(&config, &["you"], &inputs);
+        assert!(text.contains("regular expressions"));
+        assert!(text.contains(&format!("  {}\n", Path::new("src").join("walk.rs").display())));
+    }
+
+    #[test]
+    fn search_names_1() {
+        let config = Config::new(args(&["-r", "--name", "-i", "WALK", "src"])).unwrap();
+        let matcher = Matcher::literal("WALK", config.case);
+        let inputs = list_inputs(&config, &config.filenames);
+        let mut out = Vec::<u8>::new();
+        assert_eq!(Ok(1), search_names(&config, &matcher, &inputs, &mut out));
+        assert_eq!(format!("{}\n", Path::new("src").join("walk.rs").display()), 
+                   String::from_utf8(out).unwrap());
+
+        // the directories are not matched
+        let matcher = Matcher::literal("src", config.case);
+        assert_eq!(Ok(0), search_names(&config, &matcher, &inputs, &mut Vec::<u8>::new()));
+        let inputs = [Input::Directory("src".to_string())];
+        assert_eq!(Err(MinigrepError::UnreadableFiles(1)), 
+                   search_names(&config, &matcher, &inputs, &mut Vec::<u8>::new()));
+    }
+
+    #[test]
+    fn split_terms_1() {
+        assert_eq!(vec!["foo", "bar", "baz"], split_terms("foo,bar,baz"));
+        assert_eq!(vec!["1,000", "a\\b", "c\\"], split_terms("1\\,000,,a\\b,c\\"));
+        assert_eq!(Vec::<String>::new(), split_terms(","));
+    }
+
+    #[test]
+    fn config_patterns() {
+        let config = Config::new(args(&["-e", "you", "--pattern=frog", "poem.txt", "-"])).unwrap();
+        assert_eq!(vec!["you".to_string(), "frog".to_string()], config.queries);
+        assert_eq!(vec!["poem.txt".to_string(), "-".to_string()], config.filenames);
+        assert_eq!(Err(MinigrepError::MissingFilename), Config::new(args(&["-e", "you"])));
+        let config = Config::new(args(&["--any", "you,a\\, b", "-e", "frog", "poem.txt"])).unwrap();
+        assert_eq!(vec!["you".to_string(), "a, b".to_string(), "frog".to_string()], config.queries);
+        assert_eq!(vec!["poem.txt".to_string()], config.filenames);
+        let config = Config::new(args(&["-f", "patterns.txt", "-e", "you", "poem.txt"])).unwrap();
+        assert_eq!(vec!["patterns.txt".to_string()], config.pattern_files);
+        assert_eq!(vec!["poem.txt".to_string()], config.filenames);
+    }
+
+    #[test]
+    fn read_patterns_not_found() {
+        assert_eq!(Err(MinigrepError::PatternFileNotFound("not_a_file.txt".to_string())), 
+                   read_patterns("not_a_file.txt"));
+    }
+
+    #[test]
+    fn normalize_whitespace_offsets() {
+        let (normalized, offsets) = normalize_whitespace_with_offsets("a \t b é");
+        assert_eq!("a b é", normalized);
+        assert_eq!(vec![0, 1, 4, 5, 6, 6, 8], offsets);
+        assert_eq!(("".to_string(), vec![0]), normalize_whitespace_with_offsets(""));
+    }
+
+    #[test]
+    fn search_several() {
+        let contents = vec!["Rust:".to_string(),
+                            "safe, fast, productive.".to_string(), 
+                            "Pick three.".to_string()];
+        assert_eq!(vec![(0, vec![0]), (1, vec![6])], search(&["Rust", "fast"], &contents));
+        assert_eq!(vec![(0, vec![0]), (1, vec![0, 6])], 
+                   search_case_insensitive(&["rust", "safe", "fa"], &contents));
+    }
+
+    #[test]
+    fn builder_1() {
+        assert_eq!(Err(MinigrepError::MissingQuery), Config::builder().filename("poem.txt").build());
+        assert_eq!(Err(MinigrepError::MissingFilename), Config::builder().query("you").build());
+        let config = Config::builder().query("you").filename("poem.txt").filename("-").style(1)
+                                      .case_sensitive(false).total(true).build().unwrap();
+        assert_eq!(vec!["you".to_string()], config.queries);
+        assert_eq!(vec!["poem.txt".to_string(), "-".to_string()], config.filenames);
+        assert_eq!(vec![1], config.styles);
+        assert_eq!(CaseMode::Insensitive, config.case);
+        assert!(config.count && config.total);
+    }
+
+    #[test]
+    fn search_reader_timeout() {
+        let config = Config::builder().query("you").filename("-").count(true).timeout(0).build()
+                                      .unwrap();
+        let matcher = Matcher::literal("you", CaseMode::Sensitive);
+        let past = Instant::now() - Duration::from_millis(1);
+        let mut out = Vec::<u8>::new();
+        assert_eq!(Err(MinigrepError::Timeout(0)),
+                   search_reader(&config, &matcher, None, "-", "you\n".as_bytes(), 
+                                 &SearchState::new(Some(past)), &mut out));
+        let state = SearchState::new(None);
+        assert_eq!(Ok(1), search_reader(&config, &matcher, None, "-", "you\nme\n".as_bytes(), 
+                                        &state, &mut out));
+        assert_eq!(b"1\n".to_vec(), out);
+        assert_eq!(2, state.lines_scanned.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn search_reader_binary() {
+        let matcher = Matcher::literal("you", CaseMode::Sensitive);
+        let content = b"\x7fELF\0\xFF\nyou\n";
+        let mut builder = Config::builder();
+        builder.query("you").filename("bin");
+        let mut out = Vec::<u8>::new();
+        let config = builder.build().unwrap();
+        let state = SearchState::new(None);
+        assert_eq!(Ok(1), search_reader(&config, &matcher, None, "bin", &content[..], &state, 
+                                        &mut out));
+        assert_eq!(b"Binary file bin matches\n".to_vec(), out);
+        let config = builder.binary_mode(BinaryMode::WithoutMatch).build().unwrap();
+        assert_eq!(Ok(0), search_reader(&config, &matcher, None, "bin", &content[..], &state, 
+                                        &mut io::sink()));
+        let config = builder.binary_mode(BinaryMode::Text).build().unwrap();
+        assert_eq!(Err(MinigrepError::InvalidUtf8("bin".to_string())), 
+                   search_reader(&config, &matcher, None, "bin", &content[..], &state, 
+                                 &mut io::sink()));
+    }
+
+    #[test]
+    fn run_to_writer_several_files() {
+        let mut builder = Config::builder();
+        builder.query("frog").filename("poem.txt").filename("poem.txt").suppress_filename(true);
+        let mut sequential = Vec::<u8>::new();
+        assert!(run_to_writer(builder.threads(1).build().unwrap(), &mut sequential).unwrap());
+        assert_eq!(b"How public, like a frog\n\nHow public, like a frog\n".to_vec(), sequential);
+        let mut parallel = Vec::<u8>::new();
+        assert!(run_to_writer(builder.threads(2).build().unwrap(), &mut parallel).unwrap());
+        assert_eq!(sequential, parallel);
+    }
+
+    #[test]
+    fn config_threads() {
+        let config = Config::new(args(&["--threads", "4", "you", "poem.txt"])).unwrap();
+        assert_eq!(Some(4), config.threads);
+        assert_eq!(Err(MinigrepError::InvalidValue("--threads".to_string(), "0".to_string())),
+                   Config::new(args(&["--threads=0", "you", "poem.txt"])));
+        let config = Config::new(args(&["--expand-tabs", "you", "poem.txt"])).unwrap();
+        assert_eq!(Some(8), config.tab_width);
+        let config = Config::new(args(&["--expand-tabs=4", "you", "poem.txt"])).unwrap();
+        assert_eq!(Some(4), config.tab_width);
+        assert_eq!(Err(MinigrepError::InvalidValue("--expand-tabs".to_string(), "0".to_string())),
+                   Config::new(args(&["--expand-tabs=0", "you", "poem.txt"])));
+    }
+
+    #[test]
+    fn run_with_builder() {
+        let config = Config::builder().query("you").filename("poem.txt").quiet(true).build();
+        assert!(run(config.unwrap()).unwrap());
+        let config = Config::builder().query("not in the poem").filename("poem.txt").quiet(true)
+                                      .build();
+        assert!(!run(config.unwrap()).unwrap());
+    }
+
+    #[test]
+    fn read_file_not_found() {
+        assert_eq!(Err(MinigrepError::FileNotFound("not_a_file.txt".to_string())), 
+                   read_file("not_a_file.txt"));
+    }
+
+    #[test]
+    fn read_file_lines() {
+        let contents = read_file("poem.txt").unwrap();
+        let lines = contents.lines();
+        assert_eq!("How public, like a frog", lines[6]);
+        assert_eq!(vec![(6, vec![19])], search(&["frog"], &lines));
+        assert_eq!(contents, read_file(Path::new("poem.txt")).unwrap());
+        assert_eq!(contents, read_file(String::from("poem.txt")).unwrap());
+    }
+
+    #[test]
+    fn read_file_invalid_utf8() {
+        let path = env::temp_dir().join(format!("minigrep_invalid_utf8_{}.txt", std::process::id()));
+        fs::write(&path, b"valid line\ninvalid \xFF line\n").unwrap();
+        let filename = path.to_str().unwrap();
+        let res = read_file(filename);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Err(MinigrepError::InvalidUtf8(filename.to_string())), res);
+    }
+
+    #[test]
+    fn read_file_binary() {
+        let path = env::temp_dir().join(format!("minigrep_binary_{}.bin", std::process::id()));
+        fs::write(&path, b"text\0line\n").unwrap();
+        let filename = path.to_str().unwrap();
+        let res = read_file(filename);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Err(MinigrepError::Binary(filename.to_string())), res);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn read_file_invalid_gzip() {
+        let path = env::temp_dir().join(format!("minigrep_invalid_gzip_{}.gz", std::process::id()));
+        fs::write(&path, b"not compressed\n").unwrap();
+        let filename = path.to_str().unwrap();
+        let res = read_file(filename);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Err(MinigrepError::Decompression(filename.to_string(), "gzip")), res);
+    }
+
+    #[test]
+    fn split_lines_1() {
+        let contents = split_lines("Rust:\nsafe, fast, productive.");
+        assert_eq!(vec!["Rust:".to_string(), "safe, fast, productive.".to_string()], contents);
+    }
+
+    #[test]
+    fn split_lines_crlf() {
+        let contents = split_lines("Rust:\r\nsafe,\r fast\r\nproductive.");
+        assert_eq!(vec!["Rust:".to_string(), "safe,\r fast".to_string(), 
+                        "productive.".to_string()], 
+                   contents);
+    }
+
+    #[test]
+    fn split_lines_trailing_newline() {
+        assert_eq!(vec!["a".to_string(), "b".to_string()], split_lines("a\nb\n"));
+        assert_eq!(vec!["a".to_string(), "b".to_string()], split_lines("a\nb"));
+        assert_eq!(vec!["a".to_string(), "".to_string()], split_lines("a\n\n"));
+    }
+
+    #[test]
+    fn split_lines_empty() {
+        let contents = split_lines("");
+        assert!(contents.is_empty());
+        assert!(search(&[""], &contents).is_empty());
     }
 }
@@ -1,17 +1,25 @@
 use std::env;
 use std::process;
 use minigrep::Config;
+use minigrep::style::{Color, ColorChoice, ColorMode, Styler};
 
 fn main() {
 
-    // configuration
-    let config = Config::new(env::args()).unwrap_or_else(|err| {
-        eprintln!("{}", err);
-        process::exit(1);
+    // configuration, from the `.minigreprc` file and the arguments; errors in them are only 
+    // coloured if the output is a terminal
+    let styler = Styler::stdout(ColorChoice::Auto, ColorMode::default());
+    let config = Config::from_env_and_args(env::args()).unwrap_or_else(|err| {
+        eprintln!("{}", styler.add_fg_named(err.to_string(), Color::Red));
+        process::exit(2);
     });
 
-    if let Err(e) = minigrep::run(config) {
-        eprintln!("Application error: {}", e);
-        process::exit(1);
+    // exit status: 0 if a line was selected, 1 if none was, and 2 on error
+    match minigrep::run(config) {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("Application error: {}", styler.add_fg_named(e.to_string(), Color::Red));
+            process::exit(2);
+        },
     };
 }
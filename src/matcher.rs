@@ -0,0 +1,573 @@
+//! Matching of the lines against the queries
+
+use regex::{Regex, RegexBuilder};
+
+use crate::CaseMode;
+use crate::error::MinigrepError;
+use crate::{find_matches, normalize_whitespace_with_offsets};
+
+/// What the lines are matched against
+#[derive(Debug)]
+pub struct Matcher {
+    pattern: Pattern,
+    whole_words: bool,
+    whole_line: bool,
+    normalize_whitespace: bool,
+    /// separator and number (starting from 1) of the only field searched, if any
+    field: Option<(String, usize)>,
+}
+
+/// The pattern of a matcher
+#[derive(Debug)]
+enum Pattern {
+    /// Literal strings, any of which may match, each with whether it is case-sensitive
+    Literal { queries: Vec<(String, bool)> },
+    /// A regular expression, made of the alternation of those given if there are several
+    Regex(Regex),
+}
+
+impl Matcher {
+    /// Create a matcher looking for a literal string
+    pub fn literal(query: &str, case: CaseMode) -> Matcher {
+        Matcher::literals(&[query], case)
+    }
+
+    /// Create a matcher looking for any of several literal strings
+    ///
+    /// With `CaseMode::Smart`, whether the search is case-sensitive is decided for each query.
+    pub fn literals<S: AsRef<str>>(queries: &[S], case: CaseMode) -> Matcher {
+        let queries = queries.iter()
+                             .map(|query| query.as_ref())
+                             .map(|query| (query.to_string(), case.is_sensitive(query)))
+                             .collect();
+        Matcher { 
+            pattern: Pattern::Literal { queries },
+            whole_words: false,
+            whole_line: false,
+            normalize_whitespace: false,
+            field: None,
+        }
+    }
+
+    /// Create a matcher looking for a regular expression
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidRegex` if the pattern can not be compiled
+    pub fn regex(pattern: &str, case: CaseMode) -> Result<Matcher, MinigrepError> {
+        Matcher::regexes(&[pattern], case)
+    }
+
+    /// Create a matcher looking for any of several regular expressions
+    ///
+    /// With `CaseMode::Smart`, whether the search is case-sensitive is decided for each pattern.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidRegex` if one of the patterns can not be compiled
+    pub fn regexes<S: AsRef<str>>(patterns: &[S], case: CaseMode) 
+        -> Result<Matcher, MinigrepError> {
+        // compile each pattern on its own first, so that the error refers to the faulty one
+        let mut groups = Vec::<String>::with_capacity(patterns.len());
+        for pattern in patterns {
+            let pattern = pattern.as_ref();
+            let case_sensitive = case.is_sensitive(pattern);
+            if let Err(e) = RegexBuilder::new(pattern).case_insensitive(!case_sensitive).build() {
+                return Err(MinigrepError::InvalidRegex(pattern.to_string(), e.to_string()));
+            }
+            let flags = if case_sensitive { "" } else { "i" };
+            groups.push(format!("(?{}:{})", flags, pattern));
+        }
+        let alternation = groups.join("|");
+        match RegexBuilder::new(&alternation).build() {
+            Ok(regex) => Ok(Matcher { 
+                pattern: Pattern::Regex(regex), 
+                whole_words: false, 
+                whole_line: false,
+                normalize_whitespace: false,
+                field: None,
+            }),
+            Err(e) => Err(MinigrepError::InvalidRegex(alternation, e.to_string())),
+        }
+    }
+
+    /// Create a matcher looking for any of several glob patterns
+    ///
+    /// Each pattern is translated by `glob_regex`; with `CaseMode::Smart`, whether the search is 
+    /// case-sensitive is decided for each pattern.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidRegex` if the translation of one of the patterns can not be compiled
+    pub fn globs<S: AsRef<str>>(patterns: &[S], case: CaseMode) 
+        -> Result<Matcher, MinigrepError> {
+        let regexes = patterns.iter()
+                              .map(|pattern| glob_regex(pattern.as_ref()))
+                              .collect::<Vec<String>>();
+        Matcher::regexes(&regexes, case)
+    }
+
+    /// Only keep the matches which are whole words if `whole_words` is `true`
+    ///
+    /// A match is a whole word if it is neither preceded nor followed by a word character 
+    /// (alphanumeric or underscore). A match which is not one does not hide those overlapping it:
+    /// `a|ab` matches `ab` in `ab,`, and the query `b-b` the end of `ab-b-b`.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidRegex` if the regular expression can not be compiled once surrounded by the 
+    ///   checks of the word boundaries
+    pub fn whole_words(mut self, whole_words: bool) -> Result<Matcher, MinigrepError> {
+        if whole_words {
+            // another alternative may be a whole word where the first one is not, so the 
+            // boundaries are checked by the regular expression; the match is its first group
+            if let Pattern::Regex(regex) = &self.pattern {
+                let bounded = format!("(?:^|[^{chars}])({})(?:[^{chars}]|$)", regex.as_str(), 
+                                      chars = WORD_CHARS);
+                match Regex::new(&bounded) {
+                    Ok(bounded) => self.pattern = Pattern::Regex(bounded),
+                    Err(e) => return Err(MinigrepError::InvalidRegex(bounded, e.to_string())),
+                }
+            }
+        }
+        self.whole_words = whole_words;
+        Ok(self)
+    }
+
+    /// Only match the lines which are equal to one of the queries if `whole_line` is `true`
+    ///
+    /// The match is then the whole line.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidRegex` if the regular expression can not be compiled once anchored
+    pub fn whole_line(mut self, whole_line: bool) -> Result<Matcher, MinigrepError> {
+        if whole_line {
+            // a match of the alternation may not be the whole line even if another one is, so the
+            // regular expression is anchored instead of checking its matches
+            if let Pattern::Regex(regex) = &self.pattern {
+                let anchored = format!("^(?:{})$", regex.as_str());
+                match Regex::new(&anchored) {
+                    Ok(anchored) => self.pattern = Pattern::Regex(anchored),
+                    Err(e) => return Err(MinigrepError::InvalidRegex(anchored, e.to_string())),
+                }
+            }
+        }
+        self.whole_line = whole_line;
+        Ok(self)
+    }
+
+    /// Consider each run of whitespace as a single space if `normalize_whitespace` is `true`
+    ///
+    /// The literal queries are normalized in the same way. The ranges returned by `find` still
+    /// refer to the original line.
+    pub fn normalize_whitespace(mut self, normalize_whitespace: bool) -> Matcher {
+        if normalize_whitespace {
+            if let Pattern::Literal { queries, .. } = &mut self.pattern {
+                for (query, _) in queries.iter_mut() {
+                    *query = crate::normalize_whitespace(query);
+                }
+            }
+        }
+        self.normalize_whitespace = normalize_whitespace;
+        self
+    }
+
+    /// Only search the field number `n` (starting from 1) of the lines, the fields being 
+    /// separated by `separator`, if `field` is `Some((separator, n))`
+    ///
+    /// The other options then apply to the field as if it was the whole line; the lines with 
+    /// fewer fields do not match. The ranges returned by `find` still refer to the whole line.
+    pub fn field(mut self, field: Option<(&str, usize)>) -> Matcher {
+        self.field = field.map(|(separator, n)| (separator.to_string(), n));
+        self
+    }
+
+    /// Find the byte ranges of the non-overlapping matches in a line
+    ///
+    /// With several literal strings, the ranges of the matches of all of them are merged where 
+    /// they overlap.
+    pub fn find(&self, line: &str) -> Vec<(usize, usize)> {
+        match &self.field {
+            Some((separator, n)) => match field_range(line, separator, *n) {
+                Some((start, end)) => self.find_in(&line[start..end])
+                                          .into_iter()
+                                          .map(|(s, e)| (start + s, start + e))
+                                          .collect(),
+                None => Vec::new(),
+            },
+            None => self.find_in(line),
+        }
+    }
+
+    /// Find the byte ranges of the non-overlapping matches in a line or a field of a line
+    fn find_in(&self, line: &str) -> Vec<(usize, usize)> {
+        if self.normalize_whitespace {
+            let (normalized, offsets) = normalize_whitespace_with_offsets(line);
+            self.find_pattern(&normalized)
+                .into_iter()
+                .map(|(start, end)| (offsets[start], offsets[end]))
+                .collect()
+        } else {
+            self.find_pattern(line)
+        }
+    }
+
+    /// Find the byte ranges of the non-overlapping matches of the pattern in a line
+    fn find_pattern(&self, line: &str) -> Vec<(usize, usize)> {
+        match &self.pattern {
+            Pattern::Literal { queries } if self.whole_line => {
+                let whole = (0, line.len());
+                if queries.iter().any(|(query, case_sensitive)| {
+                    find_matches(query, line, *case_sensitive).first() == Some(&whole)
+                }) {
+                    vec![whole]
+                } else {
+                    Vec::new()
+                }
+            },
+            Pattern::Literal { queries } if queries.len() == 1 => {
+                self.find_literal(&queries[0].0, line, queries[0].1)
+            },
+            Pattern::Literal { queries } => {
+                let spans = queries.iter()
+                                   .flat_map(|(query, case_sensitive)| {
+                                       self.find_literal(query, line, *case_sensitive)
+                                   })
+                                   .collect();
+                merge_spans(spans)
+            },
+            Pattern::Regex(regex) if self.whole_words => {
+                // the boundary after a match may be the one before the next
+                let mut spans = Vec::<(usize, usize)>::new();
+                let mut pos = 0;
+                while let Some(m) = regex.captures_at(line, pos).and_then(|c| c.get(1)) {
+                    spans.push((m.start(), m.end()));
+                    pos = m.end() + if m.is_empty() { next_char_len(line, m.end()) } else { 0 };
+                    if pos > line.len() {
+                        break;
+                    }
+                }
+                spans
+            },
+            Pattern::Regex(regex) => regex.find_iter(line).map(|m| (m.start(), m.end())).collect(),
+        }
+    }
+
+    /// Find the byte ranges of the non-overlapping matches of a literal string in a line, 
+    /// keeping only the whole words if `self.whole_words` is `true`
+    fn find_literal(&self, query: &str, line: &str, case_sensitive: bool) -> Vec<(usize, usize)> {
+        if !self.whole_words {
+            return find_matches(query, line, case_sensitive);
+        }
+        // after a match which is not a whole word, the search starts again from its second 
+        // character, as an overlapping match may be one
+        let mut spans = Vec::<(usize, usize)>::new();
+        let mut pos = 0;
+        'search: while pos <= line.len() {
+            for (start, end) in find_matches(query, &line[pos..], case_sensitive) {
+                let (start, end) = (pos + start, pos + end);
+                if is_whole_word(line, start, end) {
+                    spans.push((start, end));
+                } else {
+                    pos = start + next_char_len(line, start);
+                    continue 'search;
+                }
+            }
+            break;
+        }
+        spans
+    }
+}
+
+/// Translate a glob pattern into a regular expression matching it anywhere in a line
+///
+/// `*` matches any sequence of characters (as many as possible), `?` any single character, and 
+/// `[...]` any of the characters between the brackets, or any other one if the first of them is 
+/// `!` or `^`; ranges such as `[a-z]` are supported. A `^` at the start of the pattern anchors it 
+/// to the start of the line, and a `$` at its end to the end of the line. All the other 
+/// characters, including a `[` without a matching `]`, are matched literally.
+pub fn glob_regex(pattern: &str) -> String {
+    translate_glob(pattern, false)
+}
+
+/// A glob pattern matched against whole file names or paths
+///
+/// The wildcards are those of `glob_regex`, except that `*` and `?` do not match `/`, while `**`
+/// matches any sequence of characters and `**/` also matches no directory at all; `^` and `$` 
+/// are matched literally.
+#[derive(Debug, Clone)]
+pub struct PathGlob(Regex);
+
+impl PathGlob {
+    /// Compile a glob pattern of file names or paths
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidRegex` if the translation of the pattern can not be compiled, for instance if 
+    ///   it has a range such as `[z-a]`
+    pub fn new(pattern: &str) -> Result<PathGlob, MinigrepError> {
+        let regex = format!("^(?:{})$", translate_glob(pattern, true));
+        match Regex::new(&regex) {
+            Ok(regex) => Ok(PathGlob(regex)),
+            Err(e) => Err(MinigrepError::InvalidRegex(pattern.to_string(), e.to_string())),
+        }
+    }
+
+    /// Whether the pattern matches the whole of a name or path
+    pub fn matches(&self, path: &str) -> bool {
+        self.0.is_match(path)
+    }
+}
+
+/// Translate a glob pattern into a regular expression, with the wildcards of `PathGlob` if 
+/// `paths` is `true` and those of `glob_regex` otherwise
+fn translate_glob(pattern: &str, paths: bool) -> String {
+    let chars = pattern.chars().collect::<Vec<char>>();
+    let mut res = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '^' if i == 0 && !paths => res.push('^'),
+            '$' if i == chars.len() - 1 && !paths => res.push('$'),
+            '*' if paths && chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    res.push_str("(?:.*/)?");
+                    i += 1;
+                } else {
+                    res.push_str(".*");
+                }
+                i += 1;
+            },
+            '*' if paths => res.push_str("[^/]*"),
+            '?' if paths => res.push_str("[^/]"),
+            '*' => res.push_str(".*"),
+            '?' => res.push('.'),
+            '[' => match class_end(&chars, i) {
+                Some(end) => {
+                    res.push_str(&class_regex(&chars[i + 1..end]));
+                    i = end;
+                },
+                None => res.push_str("\\["),
+            },
+            c => res.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    res
+}
+
+/// Index of the `]` closing the class opened by the `[` at index `start`, if any
+///
+/// A `]` right after the `[` (or after the negation) is part of the class.
+fn class_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut first = start + 1;
+    if matches!(chars.get(first), Some('!') | Some('^')) {
+        first += 1;
+    }
+    chars.iter().skip(first + 1).position(|&c| c == ']').map(|i| first + 1 + i)
+}
+
+/// Regular expression of the class made of these characters, between the brackets
+fn class_regex(chars: &[char]) -> String {
+    let (negated, chars) = match chars {
+        ['!', rest @ ..] | ['^', rest @ ..] => (true, rest),
+        _ => (false, chars),
+    };
+    let mut res = String::from(if negated { "[^" } else { "[" });
+    for (i, &c) in chars.iter().enumerate() {
+        // a `-` between two characters is a range; every other punctuation is taken literally
+        if c == '-' && i > 0 && i < chars.len() - 1 {
+            res.push('-');
+        } else if c.is_ascii_punctuation() {
+            res.push('\\');
+            res.push(c);
+        } else {
+            res.push(c);
+        }
+    }
+    res.push(']');
+    res
+}
+
+/// Byte range of the field number `n` (starting from 1) of a line, if it has that many fields
+fn field_range(line: &str, separator: &str, n: usize) -> Option<(usize, usize)> {
+    let mut start = 0;
+    for _ in 1..n {
+        start += line[start..].find(separator)? + separator.len();
+    }
+    let end = line[start..].find(separator).map_or(line.len(), |i| start + i);
+    Some((start, end))
+}
+
+/// Sort byte ranges and merge those which overlap
+fn merge_spans(mut spans: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    spans.sort_unstable();
+    let mut res = Vec::<(usize, usize)>::with_capacity(spans.len());
+    for (start, end) in spans {
+        match res.last_mut() {
+            Some(last) if start < last.1 => last.1 = last.1.max(end),
+            _ => res.push((start, end)),
+        }
+    }
+    res
+}
+
+/// Word characters, as in `is_word_char`, to put in a class of a regular expression
+const WORD_CHARS: &str = r"\p{Alphabetic}\p{N}_";
+
+/// Check if a character is a word character
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Length in bytes of the character of a line starting at byte `i`, or 1 at its end
+fn next_char_len(line: &str, i: usize) -> usize {
+    line[i..].chars().next().map_or(1, char::len_utf8)
+}
+
+/// Check that the range of a line is neither preceded nor followed by a word character
+fn is_whole_word(line: &str, start: usize, end: usize) -> bool {
+    !line[..start].chars().next_back().is_some_and(is_word_char)
+        && !line[end..].chars().next().is_some_and(is_word_char)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regex_spans() {
+        let matcher = Matcher::regex("colou?r", CaseMode::Sensitive).unwrap();
+        assert_eq!(vec![(4, 9), (13, 19)], matcher.find("the color or colour"));
+    }
+
+    #[test]
+    fn regex_invalid() {
+        assert!(matches!(Matcher::regex("(a", CaseMode::Sensitive), Err(MinigrepError::InvalidRegex(_, _))));
+    }
+
+    #[test]
+    fn regex_word_boundaries() {
+        let matcher = Matcher::regex("\\bduct\\b", CaseMode::Sensitive).unwrap();
+        assert_eq!(vec![(12, 16)], matcher.find("safe, fast, duct tape"));
+        assert!(matcher.find("productive").is_empty());
+        // the word boundaries are those of Unicode
+        assert!(matcher.find("éduct").is_empty());
+        assert_eq!(vec![(5, 9)], matcher.find("—, duct"));
+    }
+
+    #[test]
+    fn glob_translation() {
+        assert_eq!("err.*", glob_regex("err*"));
+        assert_eq!("^a.c\\.log$", glob_regex("^a?c.log$"));
+        assert_eq!("[^\\]a]x[a-z\\-]", glob_regex("[!]a]x[a-z-]"));
+        assert_eq!("\\[ab", glob_regex("[ab"));
+        assert_eq!("a\\^\\$b", glob_regex("a^$b"));
+    }
+
+    #[test]
+    fn globs() {
+        let matcher = Matcher::globs(&["err*"], CaseMode::Sensitive).unwrap();
+        assert_eq!(vec![(3, 13)], matcher.find("an error here"));
+        assert_eq!(vec![(0, 5)], matcher.find("errno"));
+        assert!(matcher.find("an eror").is_empty());
+        let matcher = Matcher::globs(&["^[ef]?ror$"], CaseMode::Sensitive).unwrap();
+        assert_eq!(vec![(0, 5)], matcher.find("error"));
+        assert!(matcher.find("an error").is_empty());
+    }
+
+    #[test]
+    fn path_globs() {
+        let matches = |pattern: &str, path: &str| PathGlob::new(pattern).unwrap().matches(path);
+        assert!(matches("*.log", "app.log"));
+        assert!(!matches("*.log", "logs/app.log"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "a/c"));
+        assert!(matches("**/build", "build"));
+        assert!(matches("**/build", "a/b/build"));
+        assert!(matches("doc/**", "doc/a/b.md"));
+        assert!(!matches("target", "targets"));
+        assert!(matches("^a$", "^a$"));
+        assert!(PathGlob::new("[z-a]").is_err());
+    }
+
+    #[test]
+    fn field() {
+        let line = "2024-01-02,error,disk error";
+        assert_eq!(Some((0, 10)), field_range(line, ",", 1));
+        assert_eq!(Some((17, 27)), field_range(line, ",", 3));
+        assert_eq!(None, field_range(line, ",", 4));
+        assert_eq!(Some((3, 3)), field_range("a::::b", "::", 2));
+
+        let matcher = Matcher::literal("error", CaseMode::Sensitive).field(Some((",", 3)));
+        assert_eq!(vec![(22, 27)], matcher.find(line));
+        assert!(matcher.find("2024-01-02,error,ok").is_empty());
+        assert!(matcher.find("error,error").is_empty());
+        let matcher = Matcher::literal("error", CaseMode::Sensitive).whole_line(true)
+                                                                     .unwrap()
+                                                                     .field(Some((",", 2)));
+        assert_eq!(vec![(11, 16)], matcher.find(line));
+    }
+
+    #[test]
+    fn several_literals() {
+        let matcher = Matcher::literals(&["foo", "bar", "oba"], CaseMode::Sensitive);
+        assert_eq!(vec![(0, 3), (4, 10)], matcher.find("foo foobar"));
+        assert!(matcher.find("fo ba").is_empty());
+    }
+
+    #[test]
+    fn several_regexes() {
+        let matcher = Matcher::regexes(&["colou?r", "o+r"], CaseMode::Sensitive).unwrap();
+        assert_eq!(vec![(4, 9), (10, 12)], matcher.find("the color or"));
+        assert!(matches!(Matcher::regexes(&["a", "(a"], CaseMode::Sensitive),
+                         Err(MinigrepError::InvalidRegex(pattern, _)) if pattern == "(a"));
+    }
+
+    #[test]
+    fn smart_case() {
+        let matcher = Matcher::literals(&["duct", "Pick"], CaseMode::Smart);
+        assert_eq!(vec![(0, 4), (5, 9)], matcher.find("Duct Pick pick"));
+        let matcher = Matcher::regexes(&["d.ct", "P.ck"], CaseMode::Smart).unwrap();
+        assert_eq!(vec![(0, 4), (5, 9)], matcher.find("Duct Pick pick"));
+    }
+
+    #[test]
+    fn normalized_whitespace() {
+        let matcher = Matcher::literal("foo  bar", CaseMode::Sensitive).normalize_whitespace(true);
+        assert_eq!(vec![(1, 11)], matcher.find(" foo \t  bar"));
+        assert!(Matcher::literal("foo  bar", CaseMode::Sensitive).find(" foo \t  bar").is_empty());
+        let matcher = Matcher::literal("o ", CaseMode::Sensitive).normalize_whitespace(true);
+        assert_eq!(vec![(2, 6)], matcher.find("foo   bar"));
+    }
+
+    #[test]
+    fn whole_words() {
+        let matcher = Matcher::literal("duct", CaseMode::Insensitive).whole_words(true).unwrap();
+        assert_eq!(vec![(22, 26)], matcher.find("productive, the Duct_ duct."));
+        assert!(matcher.find("productive").is_empty());
+        assert_eq!(vec![(0, 4)], matcher.find("duct"));
+        let matcher = Matcher::literal("b-b", CaseMode::Sensitive).whole_words(true).unwrap();
+        assert_eq!(vec![(3, 6)], matcher.find("ab-b-b"));
+        let matcher = Matcher::regex("a|ab", CaseMode::Sensitive).unwrap().whole_words(true);
+        let matcher = matcher.unwrap();
+        assert_eq!(vec![(0, 2)], matcher.find("ab,"));
+        assert_eq!(vec![(0, 1), (2, 3), (4, 6)], matcher.find("a a ab"));
+        assert!(matcher.find("abc a_").is_empty());
+    }
+
+    #[test]
+    fn whole_line() {
+        let matcher = Matcher::literals(&["you", "nobody"], CaseMode::Insensitive).whole_line(true)
+                                                                                  .unwrap();
+        assert_eq!(vec![(0, 3)], matcher.find("You"));
+        assert!(matcher.find("youyou").is_empty());
+        assert!(matcher.find("Are you nobody, too?").is_empty());
+        let matcher = Matcher::regexes(&["a", "ab"], CaseMode::Sensitive).unwrap().whole_line(true);
+        let matcher = matcher.unwrap();
+        assert_eq!(vec![(0, 2)], matcher.find("ab"));
+        assert!(matcher.find("abc").is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+//! Progress of the search of a file, written to the standard error
+//!
+//! The progress is the percentage of the bytes of the file read so far (before decompression,
+//! for compressed files). It is written on a single line, rewritten at most every
+//! `MIN_INTERVAL`, and cleared when the file has been read: with the escape sequence erasing 
+//! the line if the escape sequences are allowed, by overwriting it with spaces otherwise.
+
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+/// minimum time between two updates of the progress line
+pub const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A reader writing the percentage of its content read so far to a sink
+pub struct Progress<R: Read, W: Write> {
+    inner: R,
+    sink: W,
+    name: String,
+    total: u64,
+    read: u64,
+    // whether the line can be cleared with an escape sequence
+    escapes: bool,
+    // time and percentage of the last update, if any
+    last: Option<(Instant, u64)>,
+}
+
+impl<R: Read, W: Write> Progress<R, W> {
+    /// Wrap a reader of `total` bytes, named `name` in the progress line
+    ///
+    /// The line is cleared with an escape sequence only if `escapes` is `true`.
+    pub fn new(inner: R, total: u64, name: &str, sink: W, escapes: bool) -> Progress<R, W> {
+        Progress { inner, sink, name: name.to_string(), total, read: 0, escapes, last: None }
+    }
+
+    /// Text of the progress line
+    fn line(&self, percent: u64) -> String {
+        format!("{}: {}%", self.name, percent)
+    }
+
+    /// Write the progress line if enough time has passed since the last update and the
+    /// percentage has changed
+    fn update(&mut self) {
+        let percent = percent(self.read, self.total);
+        let now = Instant::now();
+        let due = match self.last {
+            Some((time, last_percent)) => {
+                percent != last_percent && now.duration_since(time) >= MIN_INTERVAL
+            },
+            None => true,
+        };
+        if due {
+            // errors are ignored, as the progress is only informative
+            let _ = write!(self.sink, "\r{}", self.line(percent));
+            let _ = self.sink.flush();
+            self.last = Some((now, percent));
+        }
+    }
+}
+
+impl<R: Read, W: Write> Read for Progress<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        self.update();
+        Ok(n)
+    }
+}
+
+impl<R: Read, W: Write> Drop for Progress<R, W> {
+    fn drop(&mut self) {
+        // clear the progress line
+        if let Some((_, percent)) = self.last {
+            let _ = if self.escapes {
+                write!(self.sink, "\r\x1b[2K")
+            } else {
+                write!(self.sink, "\r{}\r", " ".repeat(self.line(percent).chars().count()))
+            };
+            let _ = self.sink.flush();
+        }
+    }
+}
+
+/// Percentage (from 0 to 100) of `total` bytes which `read` bytes represent
+///
+/// An empty file is 100% read.
+pub fn percent(read: u64, total: u64) -> u64 {
+    (read.min(total) * 100).checked_div(total).unwrap_or(100)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_1() {
+        assert_eq!(0, percent(0, 200));
+        assert_eq!(50, percent(100, 200));
+        assert_eq!(100, percent(200, 200));
+        assert_eq!(100, percent(300, 200));
+        assert_eq!(100, percent(0, 0));
+    }
+
+    #[test]
+    fn progress_line() {
+        let mut sink = Vec::<u8>::new();
+        let mut content = String::new();
+        {
+            let mut reader = Progress::new(&b"some\nlines\n"[..], 11, "file.txt", &mut sink, 
+                                           true);
+            reader.read_to_string(&mut content).unwrap();
+        }
+        assert_eq!("some\nlines\n", content);
+        // the first update is written at once, then the line is cleared; the others are
+        // throttled
+        let progress = String::from_utf8(sink).unwrap();
+        assert!(progress.starts_with("\rfile.txt: 100%"));
+        assert!(progress.ends_with("\r\x1b[2K"));
+    }
+
+    #[test]
+    fn progress_line_without_escapes() {
+        let mut sink = Vec::<u8>::new();
+        {
+            let mut reader = Progress::new(&b"abc"[..], 3, "a.txt", &mut sink, false);
+            reader.read_to_end(&mut Vec::new()).unwrap();
+        }
+        let progress = String::from_utf8(sink).unwrap();
+        assert!(progress.ends_with(&format!("\r{}\r", " ".repeat("a.txt: 100%".len()))));
+        assert!(!progress.contains('\x1b'));
+    }
+}
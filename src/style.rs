@@ -1,14 +1,226 @@
 //! A few functions to customize the backgroud colour, foreground colour, and style of the terminal
 //! output. 
+//!
+//! Following the [NO_COLOR](https://no-color.org) convention, all of them do nothing if the 
+//! environment variable `NO_COLOR` is set to a non-empty value. The same functions are methods
+//! of `Styler`, which also decides from a `ColorChoice` whether to write colours to a given 
+//! output, and in which `ColorMode`.
+//!
+//! Colours are given in RGB. They are written as 24-bit colours by default or, depending on the
+//! mode of the styler, as the nearest colour of the 256-colour or 16-colour palette for the 
+//! terminals which do not support them.
+
+use std::env;
+use std::io;
+use std::io::IsTerminal;
+
+/// when to use colours and styles
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorChoice {
+    /// only if the output is a terminal
+    #[default]
+    Auto,
+    /// always
+    Always,
+    /// never
+    Never,
+}
+
+/// how colours are written
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    /// 24-bit colours
+    #[default]
+    TrueColor,
+    /// nearest colour of the 256-colour palette
+    Palette256,
+    /// nearest colour of the 16 ANSI colours
+    Ansi16,
+}
+
+/// whether colours and styles are enabled by the environment, *i.e.*, unless the variable 
+/// `NO_COLOR` is set to a non-empty value
+pub fn color_enabled() -> bool {
+    env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// How colours and styles are written to an output: whether they are, and in which mode
+///
+/// The free functions of this module use the default styler, which writes 24-bit colours 
+/// unless `NO_COLOR` is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Styler {
+    enabled: bool,
+    mode: ColorMode,
+}
+
+impl Default for Styler {
+    fn default() -> Styler {
+        Styler::new(ColorChoice::Always, ColorMode::default(), true)
+    }
+}
+
+impl Styler {
+    /// styler following a colour choice, for an output which is a terminal if `is_terminal`
+    ///
+    /// Colours are disabled if `NO_COLOR` is set, whatever the choice.
+    pub fn new(choice: ColorChoice, mode: ColorMode, is_terminal: bool) -> Styler {
+        let enabled = color_enabled() && match choice {
+            ColorChoice::Auto => is_terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        };
+        Styler { enabled, mode }
+    }
+
+    /// styler following a colour choice, for the standard output
+    pub fn stdout(choice: ColorChoice, mode: ColorMode) -> Styler {
+        Styler::new(choice, mode, io::stdout().is_terminal())
+    }
+
+    /// styler writing neither colours nor styles
+    pub fn plain() -> Styler {
+        Styler { enabled: false, mode: ColorMode::default() }
+    }
+
+    /// whether colours and styles are written
+    pub fn enabled(self) -> bool {
+        self.enabled
+    }
+
+    /// escape sequence resetting the colours and styles, or an empty string if they are 
+    /// disabled
+    ///
+    /// This is what the `_no_reset` methods leave to the caller.
+    pub fn reset_code(self) -> &'static str {
+        if self.enabled { RESET } else { "" }
+    }
+
+    /// add colour to a string
+    pub fn add_fg(self, s: String, r: u8, g: u8, b: u8) -> String {
+        if !self.enabled {
+            return s;
+        }
+        self.add_fg_no_reset(s, r, g, b) + RESET
+    }
+
+    /// add colour to a string without resetting it at the end
+    ///
+    /// The colour then applies to whatever is written after the string, until the caller writes
+    /// `reset_code()`.
+    pub fn add_fg_no_reset(self, s: String, r: u8, g: u8, b: u8) -> String {
+        if !self.enabled {
+            return s;
+        }
+        format!("\x1b[{};1m{}",fg_code(self.mode,r,g,b),s)
+    }
+
+    /// add a named colour to a string
+    pub fn add_fg_named(self, s: String, color: Color) -> String {
+        let (r, g, b) = color.rgb();
+        self.add_fg(s, r, g, b)
+    }
+
+    /// add colour and style to a string, in a single escape sequence
+    ///
+    /// The style is ignored if it is 0 or larger than 9; the string is then only coloured.
+    pub fn add_fg_style(self, s: String, r: u8, g: u8, b: u8, style: u8) -> String {
+        self.add_fg_styles(s, r, g, b, &[style])
+    }
+
+    /// add colour and several styles to a string, in a single escape sequence
+    ///
+    /// Styles which are 0 or larger than 9 are ignored.
+    pub fn add_fg_styles(self, s: String, r: u8, g: u8, b: u8, styles: &[u8]) -> String {
+        self.add_colors_styles(s, Some((r,g,b)), None, styles)
+    }
+
+    /// add optional foreground and background colours and several styles to a string, in a 
+    /// single escape sequence
+    ///
+    /// Styles which are 0 or larger than 9 are ignored; the string is left untouched if there 
+    /// is nothing to apply.
+    pub fn add_colors_styles(self, s: String, fg: Option<(u8, u8, u8)>, 
+                             bg: Option<(u8, u8, u8)>, styles: &[u8]) -> String {
+        if !self.enabled {
+            return s;
+        }
+        let mut codes = Vec::<String>::new();
+        if let Some((r,g,b)) = fg {
+            codes.push(fg_code(self.mode,r,g,b));
+        }
+        if let Some((r,g,b)) = bg {
+            codes.push(bg_code(self.mode,r,g,b));
+        }
+        for style in styles.iter().filter(|style| (1..=9).contains(*style)) {
+            codes.push(style.to_string());
+        }
+        if codes.is_empty() {
+            return s;
+        }
+        format!("\x1b[{}m{}\x1b[0m",codes.join(";"),s)
+    }
+
+    /// add background colour to a string
+    pub fn add_bg(self, s: String, r: u8, g: u8, b: u8) -> String {
+        if !self.enabled {
+            return s;
+        }
+        self.add_bg_no_reset(s, r, g, b) + RESET
+    }
+
+    /// add background colour to a string without resetting it at the end, as `add_fg_no_reset`
+    pub fn add_bg_no_reset(self, s: String, r: u8, g: u8, b: u8) -> String {
+        if !self.enabled {
+            return s;
+        }
+        format!("\x1b[{}m{}",bg_code(self.mode,r,g,b),s)
+    }
+
+    /// add style to a string
+    pub fn add_style(self, s: String, style: u8) -> String {
+        self.add_styles(s, &[style])
+    }
+
+    /// add style to a string without resetting it at the end, as `add_fg_no_reset`
+    ///
+    /// The string is left untouched if the style is not in `STYLES`.
+    pub fn add_style_no_reset(self, s: String, style: u8) -> String {
+        if !is_valid_style(style) || !self.enabled {
+            s
+        } else {
+            format!("\x1b[{};1m{}",style,s)
+        }
+    }
+
+    /// add several styles to a string
+    ///
+    /// Styles which are not in `STYLES` are ignored.
+    pub fn add_styles(self, s: String, styles: &[u8]) -> String {
+        let codes = styles.iter()
+                          .filter(|&&style| is_valid_style(style))
+                          .map(|style| style.to_string())
+                          .collect::<Vec<String>>();
+        if codes.is_empty() || !self.enabled {
+            s
+        } else {
+            format!("\x1b[{};1m{}\x1b[0m",codes.join(";"),s)
+        }
+    }
+}
 
 /// foreground colour in RGB format
 pub fn fg(r: u8, g: u8, b: u8) {
-    print!("\x1b[38;2;{};{};{};1m",r,g,b);
+    if color_enabled() {
+        print!("\x1b[{};1m",fg_code(ColorMode::TrueColor,r,g,b));
+    }
 }
 
 /// background colour in RGB format
 pub fn bg(r: u8, g: u8, b: u8) {
-    print!("\x1b[48;2;{};{};{};1m",r,g,b);
+    if color_enabled() {
+        print!("\x1b[{};1m",bg_code(ColorMode::TrueColor,r,g,b));
+    }
 }
 
 /// style
@@ -22,29 +234,335 @@ pub fn bg(r: u8, g: u8, b: u8) {
 /// * 8 → hidden
 /// * 9 → strikethrough
 pub fn style(style: u8) {
-    print!("\x1b[{};1m",style);
+    if color_enabled() {
+        print!("\x1b[{};1m",style);
+    }
 }
 
 /// reset the style
 pub fn reset() {
-    print!("\x1b[0m");
+    print!("{}",reset_code());
+}
+
+/// escape sequence resetting the colours and styles
+pub const RESET: &str = "\x1b[0m";
+
+/// escape sequence resetting the colours and styles, or an empty string if the colours are 
+/// disabled
+///
+/// This is what the `_no_reset` functions leave to the caller.
+pub fn reset_code() -> &'static str {
+    Styler::default().reset_code()
 }
 
 /// add colour to a string
 pub fn add_fg(s: String, r: u8, g: u8, b: u8) -> String {
-    format!("\x1b[38;2;{};{};{};1m{}\x1b[0m",r,g,b,s)
+    Styler::default().add_fg(s, r, g, b)
+}
+
+/// add colour to a string without resetting it at the end
+///
+/// The colour then applies to whatever is written after the string, until the caller writes
+/// `reset_code()`.
+pub fn add_fg_no_reset(s: String, r: u8, g: u8, b: u8) -> String {
+    Styler::default().add_fg_no_reset(s, r, g, b)
+}
+
+/// code of the escape sequence setting the foreground colour in a mode
+fn fg_code(mode: ColorMode, r: u8, g: u8, b: u8) -> String {
+    match mode {
+        ColorMode::TrueColor => format!("38;2;{};{};{}",r,g,b),
+        ColorMode::Palette256 => format!("38;5;{}",rgb_to_256(r,g,b)),
+        ColorMode::Ansi16 => match rgb_to_16(r,g,b) {
+            index if index < 8 => (30 + index).to_string(),
+            index => (90 + index - 8).to_string(),
+        },
+    }
+}
+
+/// code of the escape sequence setting the background colour in a mode
+fn bg_code(mode: ColorMode, r: u8, g: u8, b: u8) -> String {
+    match mode {
+        ColorMode::TrueColor => format!("48;2;{};{};{}",r,g,b),
+        ColorMode::Palette256 => format!("48;5;{}",rgb_to_256(r,g,b)),
+        ColorMode::Ansi16 => match rgb_to_16(r,g,b) {
+            index if index < 8 => (40 + index).to_string(),
+            index => (100 + index - 8).to_string(),
+        },
+    }
+}
+
+// levels of each channel in the colour cube of the 256-colour palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// usual RGB values of the 16 ANSI colours (those of xterm)
+const ANSI_16: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), 
+    (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+    (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+/// squared distance between two colours
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// index of the nearest colour in the 256-colour palette
+///
+/// Only the colour cube (16 to 231) and the grey ramp (232 to 255) are considered, as the first
+/// 16 colours depend on the terminal.
+pub fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        v => (v - 35) / 40,
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE_LEVELS[ri as usize], CUBE_LEVELS[gi as usize], CUBE_LEVELS[bi as usize]);
+    let average = ((r as u32 + g as u32 + b as u32) / 3) as u8;
+    let grey_index = if average > 238 { 23 } else { average.saturating_sub(3) / 10 };
+    let grey_level = 8 + 10 * grey_index;
+    if distance((r, g, b), (grey_level, grey_level, grey_level)) < distance((r, g, b), cube) {
+        232 + grey_index
+    } else {
+        16 + 36 * ri + 6 * gi + bi
+    }
+}
+
+/// index (from 0 to 15) of the nearest of the 16 ANSI colours
+pub fn rgb_to_16(r: u8, g: u8, b: u8) -> u8 {
+    (0..16u8).min_by_key(|&i| distance((r, g, b), ANSI_16[i as usize])).unwrap_or(0)
+}
+
+/// named colours
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    Cyan,
+    Magenta,
+    White,
+    Black,
+}
+
+impl Color {
+    /// RGB value of the colour
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Red => (255, 0, 0),
+            Color::Green => (0, 255, 0),
+            Color::Blue => (0, 0, 255),
+            Color::Yellow => (255, 255, 0),
+            Color::Cyan => (0, 255, 255),
+            Color::Magenta => (255, 0, 255),
+            Color::White => (255, 255, 255),
+            Color::Black => (0, 0, 0),
+        }
+    }
+}
+
+/// add colour and style to a string, in a single escape sequence
+///
+/// The style is ignored if it is 0 or larger than 9; the string is then only coloured.
+pub fn add_fg_style(s: String, r: u8, g: u8, b: u8, style: u8) -> String {
+    Styler::default().add_fg_style(s, r, g, b, style)
+}
+
+/// add colour and several styles to a string, in a single escape sequence
+///
+/// Styles which are 0 or larger than 9 are ignored.
+pub fn add_fg_styles(s: String, r: u8, g: u8, b: u8, styles: &[u8]) -> String {
+    Styler::default().add_fg_styles(s, r, g, b, styles)
+}
+
+/// add optional foreground and background colours and several styles to a string, in a single 
+/// escape sequence
+///
+/// Styles which are 0 or larger than 9 are ignored; the string is left untouched if there is
+/// nothing to apply.
+pub fn add_colors_styles(s: String, fg: Option<(u8, u8, u8)>, bg: Option<(u8, u8, u8)>, 
+                         styles: &[u8]) -> String {
+    Styler::default().add_colors_styles(s, fg, bg, styles)
+}
+
+/// add a named colour to a string
+pub fn add_fg_named(s: String, color: Color) -> String {
+    Styler::default().add_fg_named(s, color)
+}
+
+/// parse a colour in hexadecimal format
+///
+/// The colour may be given with 6 digits (`ff8800`) or 3 digits (`f80`, equivalent to `ff8800`),
+/// with an optional leading `#`.
+pub fn parse_hex(s: &str) -> Result<(u8, u8, u8), String> {
+    let digits = s.strip_prefix('#').unwrap_or(s);
+    let error = format!("Invalid colour {}: expected 3 or 6 hexadecimal digits", s);
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(error);
+    }
+    let channel = |hex: &str| u8::from_str_radix(hex, 16).unwrap();
+    match digits.len() {
+        3 => {
+            let double = |i: usize| channel(&digits[i..i+1].repeat(2));
+            Ok((double(0), double(1), double(2)))
+        },
+        6 => Ok((channel(&digits[0..2]), channel(&digits[2..4]), channel(&digits[4..6]))),
+        _ => Err(error),
+    }
 }
 
 /// add background colour to a string
 pub fn add_bg(s: String, r: u8, g: u8, b: u8) -> String {
-    format!("\x1b[40;2;{};{};{};1m{}\x1b[0m",r,g,b,s)
+    Styler::default().add_bg(s, r, g, b)
+}
+
+/// add background colour to a string without resetting it at the end, as `add_fg_no_reset`
+pub fn add_bg_no_reset(s: String, r: u8, g: u8, b: u8) -> String {
+    Styler::default().add_bg_no_reset(s, r, g, b)
+}
+
+/// styles which can be applied: none (0), bold (1), dimmed (2), italic (3), underline (4), 
+/// blink (5), reversed (7), hidden (8), and strikethrough (9)
+pub const STYLES: [u8; 9] = [0, 1, 2, 3, 4, 5, 7, 8, 9];
+
+/// whether a style is one of `STYLES`
+pub fn is_valid_style(style: u8) -> bool {
+    STYLES.contains(&style)
 }
 
 /// add style to a string
 pub fn add_style(s: String, style: u8) -> String {
-    if style <= 9 {
-        return format!("\x1b[{};1m{}\x1b[0m",style,s);
-    } else {
-        return s;
+    Styler::default().add_style(s, style)
+}
+
+/// add style to a string without resetting it at the end, as `add_fg_no_reset`
+///
+/// The string is left untouched if the style is not in `STYLES`.
+pub fn add_style_no_reset(s: String, style: u8) -> String {
+    Styler::default().add_style_no_reset(s, style)
+}
+
+/// add several styles to a string
+///
+/// Styles which are not in `STYLES` are ignored.
+pub fn add_styles(s: String, styles: &[u8]) -> String {
+    Styler::default().add_styles(s, styles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_fg_named_1() {
+        assert_eq!(add_fg("error".to_string(), 255, 0, 0), 
+                   add_fg_named("error".to_string(), Color::Red));
+    }
+
+    #[test]
+    fn add_fg_style_1() {
+        assert_eq!("\x1b[38;2;255;0;0;1mtext\x1b[0m".to_string(), 
+                   add_fg_style("text".to_string(), 255, 0, 0, 1));
+        assert_eq!("\x1b[38;2;255;0;0mtext\x1b[0m".to_string(), 
+                   add_fg_style("text".to_string(), 255, 0, 0, 0));
+    }
+
+    #[test]
+    fn add_fg_styles_1() {
+        assert_eq!("\x1b[38;2;255;0;0;1;4mtext\x1b[0m".to_string(), 
+                   add_fg_styles("text".to_string(), 255, 0, 0, &[1, 0, 4, 12]));
+        assert_eq!(add_fg_style("text".to_string(), 255, 0, 0, 0), 
+                   add_fg_styles("text".to_string(), 255, 0, 0, &[]));
+    }
+
+    #[test]
+    fn add_bg_1() {
+        assert_eq!("\x1b[48;2;255;136;0mtext\x1b[0m".to_string(), 
+                   add_bg("text".to_string(), 255, 136, 0));
+    }
+
+    #[test]
+    fn add_styles_1() {
+        assert_eq!("\x1b[1;4;1mtext\x1b[0m".to_string(), add_styles("text".to_string(), &[1, 4]));
+        assert_eq!("\x1b[1;4;1mtext\x1b[0m".to_string(), 
+                   add_styles("text".to_string(), &[1, 12, 4]));
+        assert_eq!("text".to_string(), add_styles("text".to_string(), &[10]));
+        assert_eq!("text".to_string(), add_styles("text".to_string(), &[6]));
+        assert_eq!(add_style("text".to_string(), 3), add_styles("text".to_string(), &[3]));
+    }
+
+    #[test]
+    fn no_reset() {
+        assert_eq!("\x1b[38;2;255;0;0;1mtext", add_fg_no_reset("text".to_string(), 255, 0, 0));
+        assert_eq!(add_fg("text".to_string(), 255, 0, 0), 
+                   add_fg_no_reset("text".to_string(), 255, 0, 0) + reset_code());
+        assert_eq!("\x1b[48;2;0;0;255mtext", add_bg_no_reset("text".to_string(), 0, 0, 255));
+        assert_eq!(add_bg("text".to_string(), 0, 0, 255), 
+                   add_bg_no_reset("text".to_string(), 0, 0, 255) + RESET);
+        assert_eq!("\x1b[4;1mtext", add_style_no_reset("text".to_string(), 4));
+        assert_eq!(add_style("text".to_string(), 4), 
+                   add_style_no_reset("text".to_string(), 4) + RESET);
+        assert_eq!("text", add_style_no_reset("text".to_string(), 6));
+    }
+
+    #[test]
+    fn styler() {
+        let always = Styler::new(ColorChoice::Always, ColorMode::Ansi16, false);
+        assert_eq!(always.enabled(), color_enabled());
+        if always.enabled() {
+            assert_eq!("\x1b[91;1mtext\x1b[0m", always.add_fg_named("text".to_string(), Color::Red));
+        }
+        for styler in [Styler::new(ColorChoice::Auto, ColorMode::TrueColor, false), 
+                       Styler::new(ColorChoice::Never, ColorMode::TrueColor, true), 
+                       Styler::plain()] {
+            assert!(!styler.enabled());
+            assert_eq!("text", styler.add_fg_styles("text".to_string(), 255, 0, 0, &[1]));
+            assert_eq!("", styler.reset_code());
+        }
+    }
+
+    #[test]
+    fn rgb_to_256_1() {
+        assert_eq!(16, rgb_to_256(0, 0, 0));
+        assert_eq!(196, rgb_to_256(255, 0, 0));
+        assert_eq!(46, rgb_to_256(0, 255, 0));
+        assert_eq!(21, rgb_to_256(0, 0, 255));
+        assert_eq!(231, rgb_to_256(255, 255, 255));
+        assert_eq!(244, rgb_to_256(128, 128, 128));
+        assert_eq!(208, rgb_to_256(255, 136, 0));
+    }
+
+    #[test]
+    fn rgb_to_16_1() {
+        assert_eq!(0, rgb_to_16(0, 0, 0));
+        assert_eq!(1, rgb_to_16(200, 10, 0));
+        assert_eq!(9, rgb_to_16(255, 0, 0));
+        assert_eq!(13, rgb_to_16(255, 0, 255));
+        assert_eq!(15, rgb_to_16(255, 255, 255));
+    }
+
+    #[test]
+    fn color_codes() {
+        assert_eq!("38;2;255;0;0", fg_code(ColorMode::TrueColor, 255, 0, 0));
+        assert_eq!("38;5;196", fg_code(ColorMode::Palette256, 255, 0, 0));
+        assert_eq!("91", fg_code(ColorMode::Ansi16, 255, 0, 0));
+        assert_eq!("31", fg_code(ColorMode::Ansi16, 200, 0, 0));
+        assert_eq!("48;5;46", bg_code(ColorMode::Palette256, 0, 255, 0));
+        assert_eq!("102", bg_code(ColorMode::Ansi16, 0, 255, 0));
+        assert_eq!("40", bg_code(ColorMode::Ansi16, 0, 0, 0));
+    }
+
+    #[test]
+    fn parse_hex_1() {
+        assert_eq!(Ok((255, 136, 0)), parse_hex("ff8800"));
+        assert_eq!(Ok((255, 136, 0)), parse_hex("#FF8800"));
+        assert_eq!(Ok((255, 136, 0)), parse_hex("f80"));
+        assert!(parse_hex("ff880").is_err());
+        assert!(parse_hex("gg8800").is_err());
+        assert!(parse_hex("").is_err());
     }
 }
@@ -0,0 +1,219 @@
+//! Traversal of directories for the recursive search
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::gitignore;
+use crate::gitignore::Gitignore;
+use crate::matcher::PathGlob;
+use crate::style::{Color, Styler};
+
+/// How the directories are traversed
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// only descend this number of levels into the subdirectories, 0 meaning only the direct 
+    /// entries of the directory
+    pub max_depth: Option<usize>,
+    /// follow the symbolic links to directories
+    pub follow: bool,
+    /// skip the entries ignored by the `.gitignore` files, and the `.git` directories
+    pub gitignore: bool,
+    /// if not empty, only list the files whose name matches one of these glob patterns
+    pub include: Vec<PathGlob>,
+    /// skip the files whose name matches one of these glob patterns
+    pub exclude: Vec<PathGlob>,
+    /// how the warnings are coloured
+    pub styler: Styler,
+}
+
+impl Options {
+    /// Whether a file is listed according to `include` and `exclude`, given its name
+    fn selects(&self, name: &str) -> bool {
+        let matches = |globs: &[PathGlob]| globs.iter().any(|glob| glob.matches(name));
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+}
+
+/// State of the traversal
+struct Walk<'a> {
+    options: &'a Options,
+    /// canonical paths of the directories being traversed, used to detect cycles
+    ancestors: Vec<PathBuf>,
+    /// `.gitignore` files of the directories being traversed
+    gitignores: Vec<Gitignore>,
+    files: Vec<PathBuf>,
+}
+
+/// List the regular files in a directory and its subdirectories
+///
+/// The entries of each directory are sorted by name before descending into them, so that the
+/// result does not depend on the order in which the system returns them. Symbolic links to 
+/// directories are only followed with `options.follow`; a directory is then skipped if it is one
+/// of those it is in, to avoid cycles. With `options.gitignore`, the rules of the `.gitignore`
+/// files in `dir` and its subdirectories apply relative to the directory containing each of them.
+/// The patterns of `options.include` and `options.exclude` only apply to the names of the files,
+/// not to the directories.
+///
+/// # Warnings
+///
+/// * `Could not read the directory` if a directory can not be read; its content is skipped
+/// * `Skipping the directory` if following a symbolic link leads to a cycle
+pub fn list_files(dir: &Path, options: &Options) -> Vec<PathBuf> {
+    let mut walk = Walk { 
+        options, 
+        ancestors: Vec::new(), 
+        gitignores: Vec::new(), 
+        files: Vec::new(),
+    };
+    add_files(dir, 0, &mut walk);
+    walk.files
+}
+
+/// Add the regular files in `dir`, at depth `depth`, and its subdirectories to `walk.files`
+fn add_files(dir: &Path, depth: usize, walk: &mut Walk) {
+    let options = walk.options;
+    let warning = |message: String| {
+        eprintln!("{}", options.styler.add_fg_named(message, Color::Yellow))
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            warning(format!("WARNING: Could not read the directory {}", dir.display()));
+            return;
+        }
+    };
+
+    // following symbolic links can lead back to a directory being traversed
+    let canonical = if options.follow { fs::canonicalize(dir).ok() } else { None };
+    if let Some(canonical) = &canonical {
+        if walk.ancestors.contains(canonical) {
+            warning(format!("WARNING: Skipping the directory {}, which contains itself", 
+                            dir.display()));
+            return;
+        }
+        walk.ancestors.push(canonical.clone());
+    }
+    let gitignore = if options.gitignore { Gitignore::read(dir) } else { None };
+    let has_gitignore = gitignore.is_some();
+    walk.gitignores.extend(gitignore);
+
+    let mut entries = entries.filter_map(|entry| entry.ok()).collect::<Vec<fs::DirEntry>>();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let is_dir = match entry.file_type() {
+            Ok(file_type) => {
+                file_type.is_dir() || (options.follow && file_type.is_symlink() && path.is_dir())
+            },
+            Err(_) => false,
+        };
+        if options.gitignore && (is_dir && entry.file_name() == ".git" 
+                                 || gitignore::is_ignored(&walk.gitignores, &path, is_dir)) {
+            continue;
+        }
+        if is_dir {
+            if options.max_depth.is_none_or(|max| depth < max) {
+                add_files(&path, depth + 1, walk);
+            }
+        } else if path.is_file() && options.selects(&entry.file_name().to_string_lossy()) {
+            walk.files.push(path);
+        }
+    }
+
+    if canonical.is_some() {
+        walk.ancestors.pop();
+    }
+    if has_gitignore {
+        walk.gitignores.pop();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_files_sorted() {
+        let files = list_files(Path::new("src"), &Options::default());
+        let mut sorted = files.clone();
+        sorted.sort();
+        assert_eq!(sorted, files);
+        assert!(files.contains(&Path::new("src").join("walk.rs")));
+    }
+
+    #[test]
+    fn list_files_max_depth() {
+        let dir = std::env::temp_dir().join(format!("minigrep_max_depth_{}", std::process::id()));
+        fs::create_dir_all(dir.join("a").join("b")).unwrap();
+        fs::write(dir.join("top.txt"), "").unwrap();
+        fs::write(dir.join("a").join("middle.txt"), "").unwrap();
+        fs::write(dir.join("a").join("b").join("bottom.txt"), "").unwrap();
+        let depths = [Some(0), Some(1), None].map(|max_depth| {
+            list_files(&dir, &Options { max_depth, ..Options::default() }).len()
+        });
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!([1, 2, 3], depths);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn list_files_follow() {
+        let dir = std::env::temp_dir().join(format!("minigrep_follow_{}", std::process::id()));
+        let other = std::env::temp_dir().join(format!("minigrep_other_{}", std::process::id()));
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::create_dir_all(&other).unwrap();
+        fs::write(dir.join("a").join("file.txt"), "").unwrap();
+        fs::write(other.join("other.txt"), "").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("a").join("loop")).unwrap();
+        std::os::unix::fs::symlink(&other, dir.join("link")).unwrap();
+        let not_followed = list_files(&dir, &Options::default());
+        let followed = list_files(&dir, &Options { follow: true, ..Options::default() });
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&other).unwrap();
+        assert_eq!(vec![dir.join("a").join("file.txt")], not_followed);
+        assert_eq!(vec![dir.join("a").join("file.txt"), dir.join("link").join("other.txt")], 
+                   followed);
+    }
+
+    #[test]
+    fn list_files_include_exclude() {
+        let dir = std::env::temp_dir().join(format!("minigrep_include_{}", std::process::id()));
+        fs::create_dir_all(dir.join("src.rs")).unwrap();
+        fs::write(dir.join("main.rs"), "").unwrap();
+        fs::write(dir.join("Cargo.lock"), "").unwrap();
+        fs::write(dir.join("src.rs").join("lib.rs"), "").unwrap();
+        fs::write(dir.join("src.rs").join("notes.txt"), "").unwrap();
+        let list = |include: &[&str], exclude: &[&str]| {
+            let globs = |patterns: &[&str]| {
+                patterns.iter().map(|p| PathGlob::new(p).unwrap()).collect::<Vec<PathGlob>>()
+            };
+            let options = Options { include: globs(include), exclude: globs(exclude), 
+                                    ..Options::default() };
+            list_files(&dir, &options).len()
+        };
+        let counts = [list(&[], &[]), list(&["*.rs"], &[]), list(&[], &["*.lock", "*.txt"]), 
+                      list(&["*.rs", "*.txt"], &["main.*"]), list(&["*.[rt]*"], &["*.[!r]*"])];
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!([4, 2, 2, 2, 2], counts);
+    }
+
+    #[test]
+    fn list_files_gitignore() {
+        let dir = std::env::temp_dir().join(format!("minigrep_gitignore_{}", std::process::id()));
+        fs::create_dir_all(dir.join("build")).unwrap();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join(".gitignore"), "build/\n*.log\n").unwrap();
+        fs::write(dir.join("build").join("out.txt"), "").unwrap();
+        fs::write(dir.join("src").join("main.rs"), "").unwrap();
+        fs::write(dir.join("src").join("debug.log"), "").unwrap();
+        fs::write(dir.join("src").join(".gitignore"), "!debug.log\nmain.rs\n").unwrap();
+        let all = list_files(&dir, &Options::default());
+        let files = list_files(&dir, &Options { gitignore: true, ..Options::default() });
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(5, all.len());
+        assert_eq!(vec![dir.join(".gitignore"), dir.join("src").join(".gitignore"), 
+                        dir.join("src").join("debug.log")], 
+                   files);
+    }
+}
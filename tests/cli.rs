@@ -0,0 +1,520 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Run the minigrep binary with the given arguments, from the root of the crate
+fn minigrep(args: &[&str]) -> Output {
+    minigrep_command(args).output().expect("could not run minigrep")
+}
+
+/// Command running the minigrep binary with the given arguments, from the root of the crate
+fn minigrep_command(args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_minigrep"));
+    command
+        .args(args)
+        .env_remove("CASE_INSENSITIVE")
+        .env_remove("NO_COLOR")
+        // no `.minigreprc` in the home directory
+        .env_remove("HOME");
+    command
+}
+
+#[test]
+fn exit_status_match() {
+    let output = minigrep(&["you", "poem.txt"]);
+    assert_eq!(Some(0), output.status.code());
+}
+
+#[test]
+fn exit_status_no_match() {
+    let output = minigrep(&["not in the poem", "poem.txt"]);
+    assert_eq!(Some(1), output.status.code());
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn exit_status_missing_file() {
+    let output = minigrep(&["you", "not_a_file.txt"]);
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn exit_status_missing_file_among_several() {
+    let output = minigrep(&["you", "poem.txt", "not_a_file.txt"]);
+    assert_eq!(Some(2), output.status.code());
+    assert!(!output.stdout.is_empty());
+}
+
+// fixture shared by the tests checking the whole output
+const FOX: &str = "tests/fixtures/fox.txt";
+
+#[test]
+fn fixture_match() {
+    let output = minigrep(&["the", FOX]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("the lazy dog.\n", String::from_utf8_lossy(&output.stdout));
+    assert!(output.stderr.is_empty());
+
+    let output = minigrep(&["-i", "-n", "the", FOX]);
+    assert_eq!("1:The quick brown fox\n3:the lazy dog.\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn fixture_no_match() {
+    let output = minigrep(&["cat", FOX]);
+    assert_eq!(Some(1), output.status.code());
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn fixture_missing_file() {
+    let output = minigrep(&["the", "tests/fixtures/missing.txt"]);
+    assert_eq!(Some(2), output.status.code());
+    assert!(output.stdout.is_empty());
+    assert_eq!("Application error: Could not open the file tests/fixtures/missing.txt\n",
+               String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn no_warning_for_flags() {
+    // the flags and their values are not counted among the positional arguments
+    for args in [&["-i", "you", "poem.txt"][..], &["-A", "1", "--style", "1", "you", "poem.txt"], 
+                 &["you", "-B", "2", "poem.txt", "--max-count=3"]] {
+        let output = minigrep(args);
+        assert_eq!(Some(0), output.status.code());
+        assert!(output.stderr.is_empty(), "{:?}", args);
+    }
+
+    // a genuinely unexpected trailing argument is still reported
+    let output = minigrep(&["you", "poem.txt", "6"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("WARNING: Unsupported style 6"));
+}
+
+#[test]
+fn exit_status_quiet() {
+    assert_eq!(Some(0), minigrep(&["-q", "you", "poem.txt", "not_a_file.txt"]).status.code());
+    assert_eq!(Some(1), minigrep(&["-q", "not in the poem", "poem.txt"]).status.code());
+}
+
+#[test]
+fn no_color() {
+    let args = ["-n", "--color=always", "--color", "ff8800", "you", "poem.txt", "not_a_file.txt", "1"];
+    let output = minigrep(&args);
+    assert!(output.stdout.contains(&b'\x1b'));
+    assert!(output.stderr.contains(&b'\x1b'));
+
+    let output = minigrep_command(&args).env("NO_COLOR", "1").output().unwrap();
+    assert!(!output.stdout.is_empty());
+    assert!(!output.stdout.contains(&b'\x1b'));
+    assert!(!output.stderr.is_empty());
+    assert!(!output.stderr.contains(&b'\x1b'));
+}
+
+#[test]
+fn color_choice() {
+    let output = minigrep(&["-n", "you", "poem.txt", "1"]);
+    assert!(!output.stdout.is_empty());
+    assert!(!output.stdout.contains(&b'\x1b'));
+
+    let output = minigrep(&["-n", "--color=never", "you", "poem.txt", "1"]);
+    assert!(!output.stdout.contains(&b'\x1b'));
+
+    let output = minigrep(&["-n", "--color", "always", "you", "poem.txt", "1"]);
+    assert!(output.stdout.contains(&b'\x1b'));
+}
+
+#[test]
+fn fixed_strings_over_regex() {
+    let output = minigrep(&["-E", "n)", "poem.txt"]);
+    assert_eq!(Some(2), output.status.code());
+    let output = minigrep(&["-E", "-F", "n)", "poem.txt"]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("(Emily Dickinson)\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn glob_patterns() {
+    let output = minigrep(&["--glob", "b?g!", "poem.txt"]);
+    assert_eq!("To an admiring bog!\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--glob", "-n", "^[!IT]*body", "poem.txt"]);
+    assert_eq!("2:Are you nobody, too?\n6:How dreary to be somebody!\n", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--glob", "-F", "b?g!", "poem.txt"]);
+    assert_eq!(Some(1), output.status.code());
+}
+
+#[test]
+fn max_line_length() {
+    let output = minigrep(&["--max-line-length", "10", "-n", "bog", "poem.txt"]);
+    assert_eq!("9:…iring bog!\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--max-line-length=12", "Emily", "poem.txt"]);
+    assert_eq!("(Emily Dicki…\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--max-line-length", "0", "bog", "poem.txt"]);
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn several_patterns() {
+    let output = minigrep(&["-n", "-e", "frog", "-e", "bog", "poem.txt"]);
+    assert_eq!("7:How public, like a frog\n9:To an admiring bog!\n",
+               String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn highlight_context() {
+    let output = minigrep(&["--color=always", "-A", "1", "frog", "poem.txt"]);
+    assert_eq!("How public, like a frog\nTo tell your name the livelong day\n",
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--color=always", "--highlight-context", "-A", "1", "frog", "poem.txt"]);
+    assert_eq!("How public, like a frog\n\x1b[2;1mTo tell your name the livelong day\x1b[0m\n",
+               String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn context_separator() {
+    // lines 3 and 4 are merged into a single group, separated from that of lines 7 and 8
+    let args = ["-n", "-A", "1", "-e", "Then", "-e", "banish", "-e", "frog", "poem.txt"];
+    let output = minigrep(&args);
+    assert_eq!("3:Then there's a pair of us - don't tell!\n4:They'd banish us, you know.\n5-\n--\n\
+                7:How public, like a frog\n8-To tell your name the livelong day\n",
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&[&args[..], &["--context-separator", "~~"]].concat());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\n5-\n~~\n7:"));
+    let output = minigrep(&[&args[..], &["--no-separator"]].concat());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\n5-\n7:"));
+}
+
+#[test]
+fn passthrough() {
+    let output = minigrep(&["--passthrough", "--color=always", "--style", "4", "fox", FOX]);
+    assert_eq!("The quick brown \x1b[4;1mfox\x1b[0m\njumps over\nthe lazy dog.\n", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--passthrough", "-n", "--style", "4", "fox", FOX]);
+    assert_eq!("1:The quick brown fox\n2-jumps over\n3-the lazy dog.\n", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--passthrough", "--color=never", "--style", "4", "fox", FOX]);
+    assert_eq!(std::fs::read_to_string(FOX).unwrap(), String::from_utf8_lossy(&output.stdout));
+    let output = minigrep_command(&["--passthrough", "--color=always", "--style", "4", "fox", FOX])
+        .env("NO_COLOR", "1").output().unwrap();
+    assert_eq!(std::fs::read_to_string(FOX).unwrap(), String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn files_with_matches() {
+    let output = minigrep(&["-l", "--color=always", "you", "poem.txt", "Cargo.toml", "-"]);
+    assert_eq!("poem.txt\n", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(Some(0), output.status.code());
+    let output = minigrep(&["-l", "--color=always", "--color=f00", "you", "poem.txt"]);
+    assert_eq!("\x1b[38;2;255;0;0;1mpoem.txt\x1b[0m\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-l", "nothing like this", "poem.txt", "Cargo.toml"]);
+    assert!(output.stdout.is_empty());
+    assert_eq!(Some(1), output.status.code());
+}
+
+#[test]
+fn files_without_match() {
+    let output = minigrep(&["-L", "you", "poem.txt", "Cargo.toml"]);
+    assert_eq!("Cargo.toml\n", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(Some(0), output.status.code());
+    let output = minigrep(&["-L", "you", "poem.txt"]);
+    assert!(output.stdout.is_empty());
+    assert_eq!(Some(1), output.status.code());
+    let output = minigrep(&["-L", "-l", "you", "poem.txt"]);
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn trim_and_normalize_whitespace() {
+    let output = minigrep(&[" frog ", "poem.txt"]);
+    assert!(output.stdout.is_empty());
+    let output = minigrep(&["--trim", " frog ", "poem.txt"]);
+    assert_eq!("How public, like a frog\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--normalize-whitespace", "like  \t a", "poem.txt"]);
+    assert_eq!("How public, like a frog\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn byte_offset() {
+    let output = minigrep(&["-b", "nobody", "poem.txt"]);
+    assert_eq!("0:I'm nobody! Who are you?\n25:Are you nobody, too?\n",
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-b", "-o", "-n", "nobody", "poem.txt"]);
+    assert_eq!("1:4:nobody\n2:33:nobody\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn highlight_case_insensitive() {
+    let output = minigrep(&["--color=always", "-i", "NOBODY", "poem.txt", "1"]);
+    assert_eq!("I'm \x1b[1;1mnobody\x1b[0m! Who are you?\nAre you \x1b[1;1mnobody\x1b[0m, too?\n",
+               String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn highlight_whole_words() {
+    let output = minigrep(&["--color=always", "-w", "-e", "us", "-e", "you", "poem.txt", "4"]);
+    assert_eq!("I'm nobody! Who are \x1b[4;1myou\x1b[0m?\n\
+                Are \x1b[4;1myou\x1b[0m nobody, too?\n\
+                Then there's a pair of \x1b[4;1mus\x1b[0m - don't tell!\n\
+                They'd banish \x1b[4;1mus\x1b[0m, \x1b[4;1myou\x1b[0m know.\n",
+               String::from_utf8_lossy(&output.stdout));
+    // `your` is not highlighted, and its line not selected
+    let output = minigrep(&["--color=always", "-w", "-o", "you", "poem.txt"]);
+    assert_eq!("you\nyou\nyou\n", String::from_utf8_lossy(&output.stdout));
+    // `your` is selected by the second alternative although the first one matches its start
+    let output = minigrep(&["-E", "-w", "-o", "you|your", "poem.txt"]);
+    assert_eq!("you\nyou\nyou\nyour\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn filename_prefix() {
+    let output = minigrep(&["frog", "poem.txt", "Cargo.toml"]);
+    assert_eq!("poem.txt:How public, like a frog\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-h", "frog", "poem.txt", "Cargo.toml"]);
+    assert_eq!("How public, like a frog\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-H", "-c", "frog", "poem.txt"]);
+    assert_eq!("poem.txt:1\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn null_separator() {
+    let output = minigrep(&["-Z", "-l", "you", "poem.txt", "src/main.rs", "README.md"]);
+    assert_eq!("poem.txt\0README.md\0", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-Z", "-n", "frog", "poem.txt", "Cargo.toml"]);
+    assert_eq!("poem.txt\x007:How public, like a frog\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn line_null() {
+    let output = minigrep(&["--line-null", "-n", "-A", "1", "-e", "fox", "-e", "dog", FOX]);
+    assert_eq!("1:The quick brown fox\x002-jumps over\x003:the lazy dog.\x00", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--line-null", "-Z", "frog", "poem.txt", "Cargo.toml"]);
+    assert_eq!("poem.txt\x00How public, like a frog\x00", String::from_utf8_lossy(&output.stdout));
+    // the group separator is terminated in the same way
+    let output = minigrep(&["--line-null", "-h", "frog", "poem.txt", "poem.txt"]);
+    assert_eq!("How public, like a frog\x00\x00How public, like a frog\x00", 
+               String::from_utf8_lossy(&output.stdout));
+    // as are the counts and the CSV rows
+    let output = minigrep(&["--line-null", "-c", "--total", "frog", "poem.txt", "poem.txt"]);
+    assert_eq!("poem.txt:1\x00poem.txt:1\x00total:2\x00", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--line-null", "--csv", "frog", "poem.txt"]);
+    assert_eq!("line_number,text\x007,\"How public, like a frog\"\x00", 
+               String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn progress_not_terminal() {
+    // the standard error of the tests is not a terminal, so that no progress is written to it
+    let output = minigrep(&["--progress", "frog", "poem.txt"]);
+    assert_eq!("How public, like a frog\n", String::from_utf8_lossy(&output.stdout));
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn files_from_stdin() {
+    let mut child = minigrep_command(&["--files-from=-", "frog"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"poem.txt\n\nnot_a_file.txt\nCargo.toml\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!("poem.txt:How public, like a frog\n", String::from_utf8_lossy(&output.stdout));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not_a_file.txt"));
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn threads_keep_the_order() {
+    let args = ["-r", "-n", "--color=never", "fn", "src", "tests"];
+    let sequential = minigrep(&[&["--threads", "1"], &args[..]].concat());
+    let parallel = minigrep(&[&["--threads", "4"], &args[..]].concat());
+    assert!(!sequential.stdout.is_empty());
+    assert_eq!(sequential.stdout, parallel.stdout);
+    assert_eq!(Some(0), parallel.status.code());
+}
+
+#[test]
+fn line_regexp() {
+    let output = minigrep(&["-x", "-n", "-i", "how public, like a frog", "poem.txt"]);
+    assert_eq!("7:How public, like a frog\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-x", "frog", "poem.txt"]);
+    assert!(output.stdout.is_empty());
+    assert_eq!(Some(1), output.status.code());
+}
+
+#[test]
+fn count_nonmatch() {
+    let output = minigrep(&["--count-nonmatch", "the", FOX]);
+    assert_eq!("2\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-c", "-v", "-i", "the", FOX]);
+    assert_eq!("1\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn summary() {
+    let output = minigrep(&["--summary", "-i", "the", FOX, "poem.txt"]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("scanned"));
+    assert_eq!("5 matches in 2 files (14 lines scanned)\n", String::from_utf8_lossy(&output.stderr));
+    let output = minigrep(&["--summary", "-q", "the", FOX]);
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn group_separator() {
+    let output = minigrep(&["-h", "frog", "poem.txt", FOX, "poem.txt"]);
+    assert_eq!("How public, like a frog\n\nHow public, like a frog\n", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-h", "--threads=1", "--group-separator", "--", "frog", "poem.txt", 
+                            "poem.txt"]);
+    assert_eq!("How public, like a frog\n--\nHow public, like a frog\n", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-h", "--no-group-separator", "frog", "poem.txt", "poem.txt"]);
+    assert_eq!("How public, like a frog\nHow public, like a frog\n", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-c", "frog", "poem.txt", "poem.txt"]);
+    assert_eq!("poem.txt:1\npoem.txt:1\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn count_per_line() {
+    let output = minigrep(&["--count-per-line", "-n", "-i", "o", FOX]);
+    assert_eq!("1:2:The quick brown fox\n2:1:jumps over\n3:1:the lazy dog.\n", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--count-per-line", "-E", "aa|a", "poem.txt"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\n2:Then there's a pair of us"));
+}
+
+#[test]
+fn byte_range() {
+    // the lines start at the offsets 0, 20, and 31
+    let output = minigrep(&["-n", "--byte-start", "1", "--byte-end=31", "-i", "o", FOX]);
+    assert_eq!("2:jumps over\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-n", "--byte-start", "20", "-i", "the", FOX]);
+    assert_eq!("3:the lazy dog.\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--byte-start", "20", "--byte-end=10", "the", FOX]);
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn replace() {
+    let output = minigrep(&["-i", "the", FOX, "--replace", "a"]);
+    assert_eq!("a quick brown fox\na lazy dog.\n", String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["the", FOX, "--replace=a", "--color=always", "--style", "1"]);
+    assert_eq!("\x1b[1;1ma\x1b[0m lazy dog.\n", String::from_utf8_lossy(&output.stdout));
+    // the file is not modified
+    assert_eq!("The quick brown fox\njumps over\nthe lazy dog.\n", 
+               std::fs::read_to_string(FOX).unwrap());
+}
+
+#[test]
+fn search_names() {
+    let output = minigrep(&["-r", "--name", "FOX", "tests"]);
+    assert_eq!(Some(1), output.status.code());
+    assert!(output.stdout.is_empty());
+
+    let output = minigrep(&["-r", "--name", "-i", "FOX", "--include=*.txt", "tests"]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("tests/fixtures/fox.txt\n", String::from_utf8_lossy(&output.stdout));
+
+    let output = minigrep(&["-r", "--name", "-i", "FOX", "--include=*.[st]xt", "tests"]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("tests/fixtures/fox.txt\n", String::from_utf8_lossy(&output.stdout));
+
+    let output = minigrep(&["-r", "--name", "-i", "FOX", "--exclude=*.txt", "tests"]);
+    assert_eq!(Some(1), output.status.code());
+}
+
+#[test]
+fn explain() {
+    let output = minigrep(&["--explain", "-s", "the", FOX, "1"]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(output.stdout.is_empty());
+    assert_eq!("Queries: \"the\"\nKind: fixed strings\nCase: sensitive\nStyles: 1\nFiles: 1\n  \
+                tests/fixtures/fox.txt\n", 
+               String::from_utf8_lossy(&output.stderr));
+
+    let output = minigrep(&["--dry-run", "--color=always", "the", FOX]);
+    assert!(output.stderr.contains(&b'\x1b'));
+}
+
+#[test]
+fn expand_tabs() {
+    let mut child = minigrep_command(&["--expand-tabs=4", "-n", "-b", "-A", "1", "b", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"a\tb\n\tc\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    // the byte offsets are those of the original lines
+    assert_eq!("1:0:a   b\n2-4-    c\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn highlight_background() {
+    let output = minigrep(&["--color=always", "--highlight-bg", "ff0", "--color-match=000", "fox", 
+                            FOX]);
+    assert_eq!("The quick brown \x1b[38;2;0;0;0;48;2;255;255;0mfox\x1b[0m\n", 
+               String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn any_of_a_list() {
+    let output = minigrep(&["--any", "fox,dog", FOX]);
+    assert_eq!("The quick brown fox\nthe lazy dog.\n", String::from_utf8_lossy(&output.stdout));
+    // the positional arguments are all file names
+    let output = minigrep(&["--any=over", "poem.txt", FOX]);
+    assert_eq!("tests/fixtures/fox.txt:jumps over\n", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn config_file() {
+    let dir = std::env::temp_dir().join(format!("minigrep_config_file_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(".minigreprc"), "line-number = true\nignore-case = true\n").unwrap();
+    let poem = concat!(env!("CARGO_MANIFEST_DIR"), "/poem.txt");
+    let output = minigrep_command(&["FROG", poem]).current_dir(&dir).output().unwrap();
+    let overridden = minigrep_command(&["-s", "FROG", poem]).current_dir(&dir).output().unwrap();
+    std::fs::write(dir.join(".minigreprc"), "ignore-case = yes\n").unwrap();
+    let malformed = minigrep_command(&["FROG", poem]).current_dir(&dir).output().unwrap();
+    std::fs::write(dir.join(".minigreprc"), "file = \"patterns.txt\"\n").unwrap();
+    let with_patterns = minigrep_command(&["FROG", poem]).current_dir(&dir).output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!("7:How public, like a frog\n", String::from_utf8_lossy(&output.stdout));
+    assert_eq!(Some(1), overridden.status.code());
+    assert!(String::from_utf8_lossy(&malformed.stderr).contains("line 1: invalid value yes"));
+    assert_eq!(Some(2), malformed.status.code());
+    assert!(String::from_utf8_lossy(&with_patterns.stderr)
+                .contains("Invalid value --file=patterns.txt for .minigreprc"));
+    assert_eq!(Some(2), with_patterns.status.code());
+}
+
+#[test]
+fn csv_output() {
+    let output = minigrep(&["--csv", "-n", "--color=always", "public", "poem.txt"]);
+    assert_eq!("line_number,text\n7,\"How public, like a frog\"\n", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["--csv", "frog", "poem.txt", "Cargo.toml"]);
+    assert_eq!("file,line_number,text\n\"poem.txt\",7,\"How public, like a frog\"\n", 
+               String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn patterns_from_file() {
+    let mut child = minigrep_command(&["-f", "-", "-e", "admiring", "-n", "poem.txt"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"frog\n\nlivelong\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!("7:How public, like a frog\n8:To tell your name the livelong day\n\
+                9:To an admiring bog!\n", 
+               String::from_utf8_lossy(&output.stdout));
+    let output = minigrep(&["-f", "not_a_file.txt", "poem.txt"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("pattern file not_a_file.txt"));
+    assert_eq!(Some(2), output.status.code());
+}